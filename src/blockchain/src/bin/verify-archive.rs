@@ -0,0 +1,183 @@
+// Offline verifier for the game archives exported by the chain.
+// Every transaction is replayed through the same smart contract as the server, so the
+// outcome of a game can be checked by anyone holding the archive.
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
+
+use blockchain::ledger::Wagers;
+use blockchain::registry::MethodRegistry;
+use blockchain::{GameRules, SharedData};
+use fleetcore::GameArchive;
+
+/// Replay a game archive exported from /games/<id>/export and report its outcome
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Archive file
+    archive: PathBuf,
+    /// Chain config file listing older guest versions to accept besides the built-in one
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Print the chain's log messages while replaying
+    #[arg(long)]
+    verbose: bool,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let loaded = read_archive(&cli.archive)
+        .and_then(|archive| Ok((archive, MethodRegistry::from_config(cli.config.as_deref())?)));
+    let (archive, registry) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let mut problems = Vec::new();
+
+    // Receipts are verified against the image IDs known to this verifier, never those listed
+    // in the archive; an archive listing other IDs was produced with different guests
+    let version = if archive.version.is_empty() {
+        registry.current().to_string()
+    } else {
+        archive.version.clone()
+    };
+    match registry.version(&version) {
+        Some(local) => {
+            for method in &archive.image_ids {
+                match local.image_ids.iter().find(|m| m.cmd == method.cmd) {
+                    Some(m) if m.id == method.id => {}
+                    Some(m) => problems.push(format!(
+                        "Image ID of {:?} differs: {} in the archive, {} in this verifier",
+                        method.cmd, method.id, m.id
+                    )),
+                    None => {
+                        problems.push(format!("Unknown command {:?} in the archive", method.cmd))
+                    }
+                }
+            }
+        }
+        None => problems.push(format!(
+            "Guest version {} of the archive is unknown to this verifier",
+            version
+        )),
+    }
+
+    let (tx, mut rx) = broadcast::channel::<String>(16);
+    // Scans are checked against the limit the game was played with, and stakes are taken
+    // from accounts funded for every stake they deposited
+    let wagers = Wagers {
+        stake: archive.status.stake,
+        faucet: archive.status.stake * archive.status.escrow.len() as u64,
+        ..Wagers::default()
+    };
+    let shared = SharedData::new(tx, registry)
+        .with_scan_limit(archive.status.scan_limit)
+        .with_wagers(wagers);
+    for escrow in &archive.status.escrow {
+        blockchain::faucet(&shared, &escrow.account);
+    }
+    // A tournament game exists before anyone joins, reserved to the fleets paired in it
+    if let Some(tournament) = &archive.status.tournament {
+        let rules = GameRules {
+            version: version.clone(),
+            scan_limit: archive.status.scan_limit,
+            stake: archive.status.stake,
+        };
+        blockchain::reserve_game(
+            &shared,
+            &archive.gameid,
+            rules,
+            tournament,
+            &archive.status.roster,
+        );
+    }
+    let log = |rx: &mut broadcast::Receiver<String>| {
+        while let Ok(msg) = rx.try_recv() {
+            if cli.verbose {
+                println!("  {}", msg);
+            }
+        }
+    };
+
+    for (idx, data) in archive.transactions.iter().enumerate() {
+        let result = blockchain::submit(&shared, data);
+        log(&mut rx);
+        if result != "OK" {
+            problems.push(format!(
+                "Transaction {} ({:?}) rejected: {}",
+                idx + 1,
+                data.cmd,
+                result
+            ));
+        }
+    }
+    for reveal in &archive.reveals {
+        let result = blockchain::reveal(&shared, reveal.clone());
+        log(&mut rx);
+        if result != "OK" {
+            problems.push(format!("Reveal of {} rejected: {}", reveal.fleet, result));
+        }
+    }
+
+    match blockchain::game_status(&shared, &archive.gameid) {
+        Some(mut status) => {
+            // A refund is no transaction, so only the archive tells whether one happened
+            status.settled |= archive.status.settled;
+            if status.version != version {
+                problems.push(format!(
+                    "The replay verified against guest version {} instead of {}",
+                    status.version, version
+                ));
+            }
+            if status != archive.status {
+                problems.push("The replayed state differs from the exported state".to_string());
+            }
+            println!(
+                "Game {}: {} transactions replayed",
+                status.gameid,
+                archive.transactions.len()
+            );
+            println!("Players: {}", status.players.join(", "));
+            if !status.eliminated.is_empty() {
+                println!("Eliminated: {}", status.eliminated.join(", "));
+            }
+            match &status.winner {
+                Some(winner) => println!("Winner: {}", winner),
+                None => println!("No victory claimed"),
+            }
+            for revealed in &status.revealed {
+                if revealed.issues.is_empty() {
+                    println!("Revealed {}: consistent with every report", revealed.fleet);
+                } else {
+                    println!("Revealed {}:", revealed.fleet);
+                    for issue in &revealed.issues {
+                        println!("  {}", issue);
+                    }
+                }
+            }
+        }
+        None => problems.push(format!(
+            "No transaction of the archive joins game {}",
+            archive.gameid
+        )),
+    }
+
+    if problems.is_empty() {
+        println!("Archive verified");
+    } else {
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn read_archive(path: &Path) -> Result<GameArchive, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    serde_json::from_str(&text)
+        .map_err(|err| format!("Invalid archive {}: {}", path.display(), err))
+}
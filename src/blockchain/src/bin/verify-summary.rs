@@ -0,0 +1,64 @@
+// Offline verifier for game summaries: a single receipt of the summary guest stands for
+// every receipt of a game, so checking it needs neither the chain nor the game archive.
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use blockchain::registry::MethodRegistry;
+use fleetcore::GameSummary;
+
+/// Verify a game summary produced by `segcom summary` and print the result it proves
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Summary file
+    summary: PathBuf,
+    /// Chain config file listing older guest versions to accept besides the built-in one
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let loaded = read_summary(&cli.summary)
+        .and_then(|summary| Ok((summary, MethodRegistry::from_config(cli.config.as_deref())?)));
+    let (summary, registry) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let (version, journal) = match blockchain::check_summary(&registry, &summary) {
+        Ok(checked) => checked,
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Game {}: {} transactions proven with guests version {}",
+        journal.gameid, journal.transactions, version
+    );
+    for fleet in &journal.fleets {
+        println!(
+            "{}: {} hits taken, board {}",
+            fleet.fleet, fleet.hits, fleet.board
+        );
+    }
+    if !journal.eliminated.is_empty() {
+        println!("Eliminated: {}", journal.eliminated.join(", "));
+    }
+    match &journal.winner {
+        Some(winner) => println!("Winner: {}", winner),
+        None => println!("No victory claimed"),
+    }
+    println!("Summary verified");
+}
+
+fn read_summary(path: &Path) -> Result<GameSummary, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    serde_json::from_str(&text)
+        .map_err(|err| format!("Invalid summary {}: {}", path.display(), err))
+}
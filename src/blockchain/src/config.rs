@@ -0,0 +1,132 @@
+// Runtime configuration for the blockchain emulator: command line flags take
+// precedence over environment variables, which take precedence over the config file.
+use clap::Args;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use blockchain::ledger::Wagers;
+use blockchain::registry::{MethodIds, MethodRegistry};
+
+const DEFAULT_BIND: &str = "0.0.0.0:3001";
+const DEFAULT_LOG_CAPACITY: usize = 100;
+const DEFAULT_REQUEST_TIMEOUT: u64 = 60;
+const DEFAULT_MAX_BODY: usize = 8 * 1024 * 1024;
+const DEFAULT_IP_RATE: u32 = 120;
+const DEFAULT_FLEET_RATE: u32 = 30;
+const DEFAULT_SCAN_LIMIT: u32 = 2;
+
+// Options accepted on the command line, through the environment or in the config file
+#[derive(Args, Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainOptions {
+    /// TOML file with default values for the options below
+    #[arg(long, env = "SEGCOM_CHAIN_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+    /// Address the emulator listens on
+    #[arg(long, env = "SEGCOM_CHAIN_BIND")]
+    pub bind: Option<SocketAddr>,
+    /// Number of log messages buffered for slow subscribers
+    #[arg(long, env = "SEGCOM_CHAIN_LOG_CAPACITY")]
+    pub log_capacity: Option<usize>,
+    /// Seconds allowed to process a single request
+    #[arg(long, env = "SEGCOM_CHAIN_REQUEST_TIMEOUT")]
+    pub request_timeout: Option<u64>,
+    /// Largest request body accepted, in bytes
+    #[arg(long, env = "SEGCOM_CHAIN_MAX_BODY")]
+    pub max_body: Option<usize>,
+    /// Transactions, reveals and replays accepted per minute from one address, 0 for no limit
+    #[arg(long, env = "SEGCOM_CHAIN_IP_RATE")]
+    pub ip_rate: Option<u32>,
+    /// Transactions accepted per minute from one fleet of a game, 0 for no limit
+    #[arg(long, env = "SEGCOM_CHAIN_FLEET_RATE")]
+    pub fleet_rate: Option<u32>,
+    /// Scans each fleet may request in a game, 0 to disable scans; games keep the limit
+    /// they started with
+    #[arg(long, env = "SEGCOM_CHAIN_SCAN_LIMIT")]
+    pub scan_limit: Option<u32>,
+    /// Tokens each fleet stakes when joining a game, 0 for games without stakes; games keep
+    /// the stake they started with
+    #[arg(long, env = "SEGCOM_CHAIN_STAKE")]
+    pub stake: Option<u64>,
+    /// Balance the faucet tops an account up to
+    #[arg(long, env = "SEGCOM_CHAIN_FAUCET")]
+    pub faucet: Option<u64>,
+    /// Seconds without transactions after which a game nobody won may refund its stakes
+    #[arg(long, env = "SEGCOM_CHAIN_REFUND_TIMEOUT")]
+    pub refund_timeout: Option<u64>,
+    /// Older guest versions still accepted, as [[methods]] tables of hex image IDs;
+    /// only read from the config file
+    #[arg(skip)]
+    pub methods: Option<Vec<MethodIds>>,
+}
+
+// Resolved configuration used by the emulator
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    pub bind: SocketAddr,
+    pub log_capacity: usize,
+    pub request_timeout: Duration,
+    pub max_body: usize,
+    pub ip_rate: u32,
+    pub fleet_rate: u32,
+    pub scan_limit: u32,
+    pub wagers: Wagers,
+    pub methods: MethodRegistry,
+}
+
+impl ChainOptions {
+    // Fill every unset option with the value found in the config file, if any
+    pub fn resolve(self) -> Result<ChainConfig, String> {
+        let file = match &self.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+                toml::from_str::<ChainOptions>(&text)
+                    .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?
+            }
+            None => ChainOptions::default(),
+        };
+
+        Ok(ChainConfig {
+            bind: self
+                .bind
+                .or(file.bind)
+                .unwrap_or_else(|| DEFAULT_BIND.parse().unwrap()),
+            log_capacity: self
+                .log_capacity
+                .or(file.log_capacity)
+                .unwrap_or(DEFAULT_LOG_CAPACITY),
+            request_timeout: Duration::from_secs(
+                self.request_timeout
+                    .or(file.request_timeout)
+                    .unwrap_or(DEFAULT_REQUEST_TIMEOUT),
+            ),
+            max_body: self.max_body.or(file.max_body).unwrap_or(DEFAULT_MAX_BODY),
+            ip_rate: self.ip_rate.or(file.ip_rate).unwrap_or(DEFAULT_IP_RATE),
+            fleet_rate: self
+                .fleet_rate
+                .or(file.fleet_rate)
+                .unwrap_or(DEFAULT_FLEET_RATE),
+            scan_limit: self
+                .scan_limit
+                .or(file.scan_limit)
+                .unwrap_or(DEFAULT_SCAN_LIMIT),
+            wagers: {
+                let defaults = Wagers::default();
+                Wagers {
+                    stake: self.stake.or(file.stake).unwrap_or(defaults.stake),
+                    faucet: self.faucet.or(file.faucet).unwrap_or(defaults.faucet),
+                    refund_timeout: self
+                        .refund_timeout
+                        .or(file.refund_timeout)
+                        .map(Duration::from_secs)
+                        .unwrap_or(defaults.refund_timeout),
+                }
+            },
+            methods: MethodRegistry::with_versions(file.methods.as_deref().unwrap_or_default())?,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+// src/ledger.rs
+// Token ledger of the emulator. Accounts are funded by a faucet and stake tokens when their
+// fleets join a game, which holds the stakes in escrow until it pays the winners or refunds
+// every fleet.
+use fleetcore::Escrow;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const DEFAULT_FAUCET: u64 = 100;
+const DEFAULT_REFUND_TIMEOUT: u64 = 3600;
+
+// Wager rules of the games started from now on
+#[derive(Clone, Copy, Debug)]
+pub struct Wagers {
+    // Tokens each fleet stakes when joining, 0 for games without stakes
+    pub stake: u64,
+    // Balance the faucet tops an account up to
+    pub faucet: u64,
+    // Time without transactions after which an unsettled game may refund its stakes
+    pub refund_timeout: Duration,
+}
+
+impl Default for Wagers {
+    fn default() -> Self {
+        Wagers {
+            stake: 0,
+            faucet: DEFAULT_FAUCET,
+            refund_timeout: Duration::from_secs(DEFAULT_REFUND_TIMEOUT),
+        }
+    }
+}
+
+// Balances of the accounts, tokens held in escrow excluded
+#[derive(Default)]
+pub struct Ledger {
+    balances: HashMap<String, u64>,
+}
+
+impl Ledger {
+    pub fn balance(&self, account: &str) -> u64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+
+    // Top an account up to `amount`, returning the tokens minted
+    pub fn fund(&mut self, account: &str, amount: u64) -> u64 {
+        let balance = self.balances.entry(account.to_string()).or_insert(0);
+        let minted = amount.saturating_sub(*balance);
+        *balance += minted;
+        minted
+    }
+
+    pub fn debit(&mut self, account: &str, amount: u64) -> Result<(), String> {
+        let balance = self.balance(account);
+        if balance < amount {
+            return Err(format!(
+                "Account {} holds {} tokens, {} needed",
+                account, balance, amount
+            ));
+        }
+        self.balances.insert(account.to_string(), balance - amount);
+        Ok(())
+    }
+
+    pub fn credit(&mut self, account: &str, amount: u64) {
+        *self.balances.entry(account.to_string()).or_insert(0) += amount;
+    }
+
+    // Pay every stake held in `escrow` to `accounts`, split evenly, returning the pot
+    pub fn pay(&mut self, escrow: &[Escrow], accounts: &[String]) -> u64 {
+        let pot: u64 = escrow.iter().map(|e| e.amount).sum();
+        for (account, amount) in split(pot, accounts) {
+            self.credit(&account, amount);
+        }
+        pot
+    }
+
+    // Give every stake held in `escrow` back to the account that staked it
+    pub fn refund(&mut self, escrow: &[Escrow]) {
+        for e in escrow {
+            self.credit(&e.account, e.amount);
+        }
+    }
+}
+
+// Account IDs are the hex SHA256 of the account key
+pub fn is_account(account: &str) -> bool {
+    account.len() == 64
+        && account
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+// Split `pot` evenly between `accounts`; what cannot be split goes to the first one
+pub fn split(pot: u64, accounts: &[String]) -> Vec<(String, u64)> {
+    let count = accounts.len() as u64;
+    if count == 0 {
+        return Vec::new();
+    }
+    let share = pot / count;
+    accounts
+        .iter()
+        .enumerate()
+        .map(|(i, account)| {
+            let bonus = if i == 0 { pot % count } else { 0 };
+            (account.clone(), share + bonus)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|a| a.to_string()).collect()
+    }
+
+    // Fund every account with `faucet` and stake `stake` of it for a fleet of the same name
+    fn staked(ledger: &mut Ledger, names: &[&str], faucet: u64, stake: u64) -> Vec<Escrow> {
+        names
+            .iter()
+            .map(|name| {
+                ledger.fund(name, faucet);
+                ledger.debit(name, stake).unwrap();
+                Escrow {
+                    fleet: name.to_string(),
+                    account: name.to_string(),
+                    amount: stake,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_gives_the_remainder_to_the_first_account() {
+        assert_eq!(
+            split(10, &accounts(&["a", "b", "c"])),
+            [
+                ("a".to_string(), 4),
+                ("b".to_string(), 3),
+                ("c".to_string(), 3)
+            ]
+        );
+        assert_eq!(
+            split(2, &accounts(&["a", "b", "c"])),
+            [
+                ("a".to_string(), 2),
+                ("b".to_string(), 0),
+                ("c".to_string(), 0)
+            ]
+        );
+        assert_eq!(split(9, &accounts(&["a"])), [("a".to_string(), 9)]);
+        assert!(split(9, &[]).is_empty());
+    }
+
+    #[test]
+    fn debit_needs_the_balance() {
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.fund("a", 100), 100);
+        assert_eq!(ledger.fund("a", 100), 0);
+        assert!(ledger.debit("a", 101).is_err());
+        assert_eq!(ledger.balance("a"), 100);
+    }
+
+    #[test]
+    fn escrow_pays_the_winning_side() {
+        let mut ledger = Ledger::default();
+        let escrow = staked(&mut ledger, &["a", "b", "c"], 100, 25);
+        assert_eq!(ledger.balance("a"), 75);
+
+        assert_eq!(ledger.pay(&escrow, &accounts(&["b", "c"])), 75);
+        assert_eq!(ledger.balance("a"), 75);
+        assert_eq!(ledger.balance("b"), 113);
+        assert_eq!(ledger.balance("c"), 112);
+    }
+
+    #[test]
+    fn escrow_refunds_every_stake() {
+        let mut ledger = Ledger::default();
+        let escrow = staked(&mut ledger, &["a", "b"], 100, 40);
+        ledger.refund(&escrow);
+        assert_eq!(ledger.balance("a"), 100);
+        assert_eq!(ledger.balance("b"), 100);
+    }
+}
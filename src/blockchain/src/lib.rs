@@ -0,0 +1,2835 @@
+// src/lib.rs
+// Game state of the blockchain emulator and the smart contract handling each transaction.
+// The server in main.rs and the offline archive verifier share this logic.
+pub mod ledger;
+pub mod ratings;
+pub mod registry;
+pub mod tournaments;
+
+use risc0_zkvm::Digest;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
+};
+use tokio::sync::broadcast;
+
+use fleetcore::{
+    board_digest, check_fleet, moved_board, scan_area, shots_digest, teammates, AccountStatus,
+    BaseJournal, Command, CommunicationData, Escrow, FireJournal, GameArchive, GameMode,
+    GameResult, GameStatus, GameSummary, JoinJournal, LeaderboardEntry, MoveJournal, Pairing,
+    ReplayStep, ReportJournal, ResignJournal, ResignRecord, RevealData, RevealedFleet,
+    SalvoJournal, SalvoReportJournal, ScanJournal, ScanRecord, ScanRequestJournal, ShotRecord,
+    SummaryJournal, TournamentRequest, TournamentStatus, FLEET_CELLS,
+};
+use ledger::{Ledger, Wagers};
+use methods::SUMMARY_ID;
+use registry::MethodRegistry;
+use tournaments::Tournament;
+
+pub struct Player {
+    current_state: Digest,
+    // Commitment made when joining, checked against the secrets if the fleet is revealed
+    initial_state: Digest,
+    // Commitment to the shots fired so far, which the fire guest proves a new shot is not in
+    shots_state: Digest,
+    // Ledger account the join receipt proved the key of, which the fleet is rated as
+    account: Option<String>,
+}
+pub struct Game {
+    // Guest version the game was started with; every receipt must verify against it
+    version: String,
+    // Fixed by the first shot fired
+    mode: Option<GameMode>,
+    pmap: HashMap<String, Player>,
+    // Team declared by each fleet that joined with one; a team shares its turns
+    teams: BTreeMap<String, String>,
+    next_player: Option<String>,
+    next_report: Option<String>,
+    last_shot_pos: Option<u8>,
+    last_player: Option<String>,
+    shots: Vec<ShotRecord>,
+    scans: Vec<ScanRecord>,
+    // Scans each fleet may request, fixed when the game starts
+    scan_limit: u32,
+    eliminated: Vec<String>,
+    // Fleets that left the game; they are eliminated and may not act any more
+    resigned: Vec<ResignRecord>,
+    revealed: Vec<RevealedFleet>,
+    reveals: Vec<RevealData>,
+    winner: Option<String>,
+    // Every accepted transaction, in order, so that the game can be replayed and re-verified
+    history: Vec<CommunicationData>,
+    // Latest published proof standing for the whole history
+    summary: Option<GameSummary>,
+    // Tokens each fleet stakes, fixed when the game starts
+    stake: u64,
+    // Stakes held by the game, in the order the fleets joined
+    escrow: Vec<Escrow>,
+    // Set once the stakes are paid out or refunded
+    settled: bool,
+    // Time of the last accepted transaction, from which a refund becomes possible
+    updated: Instant,
+    // Tournament that paired the game, and the only fleets that may join it
+    tournament: Option<String>,
+    roster: Vec<String>,
+}
+
+// Rules a game is pinned to when it starts
+#[derive(Clone, Debug)]
+pub struct GameRules {
+    pub version: String,
+    pub scan_limit: u32,
+    pub stake: u64,
+}
+
+impl Game {
+    // Game nobody joined yet
+    fn new(rules: GameRules) -> Self {
+        Game {
+            version: rules.version,
+            mode: None,
+            pmap: HashMap::new(),
+            teams: BTreeMap::new(),
+            next_player: None,
+            next_report: None,
+            last_shot_pos: None,
+            last_player: None,
+            shots: Vec::new(),
+            scans: Vec::new(),
+            scan_limit: rules.scan_limit,
+            eliminated: Vec::new(),
+            resigned: Vec::new(),
+            revealed: Vec::new(),
+            reveals: Vec::new(),
+            winner: None,
+            history: Vec::new(),
+            summary: None,
+            stake: rules.stake,
+            escrow: Vec::new(),
+            settled: false,
+            updated: Instant::now(),
+            tournament: None,
+            roster: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedData {
+    tx: broadcast::Sender<String>,
+    gmap: Arc<Mutex<HashMap<String, Game>>>,
+    methods: Arc<MethodRegistry>,
+    scan_limit: u32,
+    // Finished games the ratings are computed from, in the order they were rated
+    results: Arc<Mutex<Vec<GameResult>>>,
+    wagers: Wagers,
+    ledger: Arc<Mutex<Ledger>>,
+    tournaments: Arc<Mutex<BTreeMap<String, Tournament>>>,
+}
+
+impl SharedData {
+    // Empty chain accepting receipts of the registered guests and broadcasting its log
+    // messages on `tx`
+    pub fn new(tx: broadcast::Sender<String>, methods: MethodRegistry) -> Self {
+        SharedData {
+            tx,
+            gmap: Arc::new(Mutex::new(HashMap::new())),
+            methods: Arc::new(methods),
+            scan_limit: 0,
+            results: Arc::new(Mutex::new(Vec::new())),
+            wagers: Wagers::default(),
+            ledger: Arc::new(Mutex::new(Ledger::default())),
+            tournaments: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    // Scans each fleet may request in the games started from now on; none by default
+    pub fn with_scan_limit(self, scan_limit: u32) -> Self {
+        SharedData { scan_limit, ..self }
+    }
+
+    // Stakes, faucet and refunds of the games started from now on; no stakes by default
+    pub fn with_wagers(self, wagers: Wagers) -> Self {
+        SharedData { wagers, ..self }
+    }
+
+    pub fn methods(&self) -> &MethodRegistry {
+        &self.methods
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.tx.subscribe()
+    }
+
+    // Broadcast a log message; having nobody following the log is not an error
+    fn log(&self, msg: String) {
+        let _ = self.tx.send(msg);
+    }
+
+    // Lock the games. A handler that panicked while holding the lock leaves every game in a
+    // state it had already validated, so the lock is recovered rather than propagated.
+    fn games(&self) -> MutexGuard<'_, HashMap<String, Game>> {
+        self.gmap
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Lock the rated games; taken while holding the games lock, never the other way round
+    fn results(&self) -> MutexGuard<'_, Vec<GameResult>> {
+        self.results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Lock the balances; taken while holding the games lock, never the other way round
+    fn ledger(&self) -> MutexGuard<'_, Ledger> {
+        self.ledger
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Lock the tournaments; taken while holding the games lock, never the other way round
+    fn tournaments(&self) -> MutexGuard<'_, BTreeMap<String, Tournament>> {
+        self.tournaments
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// Receipt of a transaction verified against the guests of its game, ready to be applied
+pub struct Verified {
+    gameid: String,
+    fleet: String,
+    version: String,
+}
+
+impl Verified {
+    pub fn gameid(&self) -> &str {
+        &self.gameid
+    }
+
+    pub fn fleet(&self) -> &str {
+        &self.fleet
+    }
+}
+
+// Verify and apply a transaction, returning "OK" or the reason it was rejected.
+// Verification is CPU bound: async callers should run this on a blocking thread.
+pub fn submit(shared: &SharedData, input_data: &CommunicationData) -> String {
+    match verify(shared, input_data) {
+        Ok(verified) => apply(shared, input_data, &verified),
+        Err(err) => err,
+    }
+}
+
+// Verify the receipt of a transaction against the guest version its game is pinned to.
+// The games are only locked to read that version, so verifications run in parallel.
+pub fn verify(shared: &SharedData, input_data: &CommunicationData) -> Result<Verified, String> {
+    let (cmd, receipt) = (input_data.cmd, &input_data.receipt);
+    // A receipt of a guest this chain does not know can never verify
+    shared.methods.claimed_version(cmd, receipt)?;
+    let Some((gameid, fleet)) = sender(input_data) else {
+        return Err(invalid_journal(
+            shared,
+            action(cmd),
+            format!("not a {:?} journal", cmd),
+        ));
+    };
+    let pinned = shared.games().get(&gameid).map(|game| game.version.clone());
+    let version = match pinned {
+        Some(version) => shared
+            .methods
+            .verify(&version, cmd, receipt)
+            .ok()
+            .map(|()| version),
+        // Only a join starts a game, pinned to the first version its receipt verifies against
+        None if cmd == Command::Join => shared
+            .methods
+            .find_version(cmd, receipt)
+            .map(str::to_string),
+        None => {
+            let msg = format!("Game {} not found", gameid);
+            shared.log(msg.clone());
+            return Err(msg);
+        }
+    };
+    match version {
+        Some(version) => Ok(Verified {
+            gameid,
+            fleet,
+            version,
+        }),
+        None => Err(invalid_receipt(shared, action(cmd))),
+    }
+}
+
+// Apply a verified transaction to the state of its game
+pub fn apply(shared: &SharedData, input_data: &CommunicationData, verified: &Verified) -> String {
+    // The game may have been started by another join since the receipt was verified
+    let pinned = shared.games().get(&verified.gameid).map(|game| {
+        (
+            game.version.clone(),
+            game.resigned.iter().any(|r| r.fleet == verified.fleet),
+        )
+    });
+    match pinned {
+        Some((version, _)) if version != verified.version => {
+            return invalid_receipt(shared, action(input_data.cmd));
+        }
+        // A fleet that resigned takes no further part in its game
+        Some((_, true)) => {
+            let msg = format!(
+                "Fleet {} resigned from game {}",
+                verified.fleet, verified.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+        _ => {}
+    }
+    let result = match input_data.cmd {
+        Command::Join => handle_join(shared, input_data, &verified.version),
+        Command::Fire => handle_fire(shared, input_data),
+        Command::Report => handle_report(shared, input_data),
+        Command::Wave => handle_wave(shared, input_data),
+        Command::Win => handle_win(shared, input_data),
+        Command::Salvo => handle_salvo(shared, input_data),
+        Command::SalvoReport => handle_salvo_report(shared, input_data),
+        Command::ScanRequest => handle_scan_request(shared, input_data),
+        Command::Scan => handle_scan(shared, input_data),
+        Command::Move => handle_move(shared, input_data),
+        Command::Resign => handle_resign(shared, input_data),
+    };
+    // Refunds are counted from the last transaction accepted in the game
+    if result == "OK" {
+        if let Some(game) = shared.games().get_mut(&verified.gameid) {
+            game.updated = Instant::now();
+        }
+    }
+    result
+}
+
+// What a command attempts, for the log
+fn action(cmd: Command) -> &'static str {
+    match cmd {
+        Command::Join => "join game",
+        Command::Fire => "fire",
+        Command::Report => "report",
+        Command::Wave => "wave",
+        Command::Win => "claim win",
+        Command::Salvo => "fire a salvo",
+        Command::SalvoReport => "report a salvo",
+        Command::ScanRequest => "request a scan",
+        Command::Scan => "answer a scan",
+        Command::Move => "move",
+        Command::Resign => "resign",
+    }
+}
+
+// Game and fleet a transaction claims to come from, read from its journal before any
+// verification; only trusted once the receipt verifies against the guests of that game
+pub fn sender(data: &CommunicationData) -> Option<(String, String)> {
+    let journal = &data.receipt.journal;
+    match data.cmd {
+        Command::Fire => journal
+            .decode::<FireJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Report => journal
+            .decode::<ReportJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Salvo => journal
+            .decode::<SalvoJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::SalvoReport => journal
+            .decode::<SalvoReportJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::ScanRequest => journal
+            .decode::<ScanRequestJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Scan => journal
+            .decode::<ScanJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Move => journal
+            .decode::<MoveJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Resign => journal
+            .decode::<ResignJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Join => journal
+            .decode::<JoinJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+        Command::Wave | Command::Win => journal
+            .decode::<BaseJournal>()
+            .ok()
+            .map(|j| (j.gameid, j.fleet)),
+    }
+}
+
+// Public state of a game, None if no fleet joined it
+pub fn game_status(shared: &SharedData, gameid: &str) -> Option<GameStatus> {
+    let gmap = shared.games();
+    let game = gmap.get(gameid)?;
+    let mut players: Vec<String> = game.pmap.keys().cloned().collect();
+    players.sort();
+    Some(GameStatus {
+        gameid: gameid.to_string(),
+        players,
+        next_player: game.next_player.clone(),
+        next_report: game.next_report.clone(),
+        last_shot_pos: game.last_shot_pos,
+        last_player: game.last_player.clone(),
+        shots: game.shots.clone(),
+        eliminated: game.eliminated.clone(),
+        revealed: game.revealed.clone(),
+        winner: game.winner.clone(),
+        version: game.version.clone(),
+        mode: game.mode.unwrap_or_default(),
+        scans: game.scans.clone(),
+        scan_limit: game.scan_limit,
+        teams: game.teams.clone(),
+        resigned: game.resigned.clone(),
+        stake: game.stake,
+        escrow: game.escrow.clone(),
+        settled: game.settled,
+        tournament: game.tournament.clone(),
+        roster: game.roster.clone(),
+    })
+}
+
+// Everything needed to check a game without this server, None if no fleet joined it
+pub fn export(shared: &SharedData, gameid: &str) -> Option<GameArchive> {
+    let status = game_status(shared, gameid)?;
+    let gmap = shared.games();
+    let game = gmap.get(gameid)?;
+    Some(GameArchive {
+        gameid: gameid.to_string(),
+        version: game.version.clone(),
+        image_ids: shared
+            .methods
+            .version(&game.version)
+            .map(|v| v.image_ids.clone())
+            .unwrap_or_default(),
+        transactions: game.history.clone(),
+        reveals: game.reveals.clone(),
+        status,
+    })
+}
+
+// Re-verify every accepted transaction of a game, in order, None if no fleet joined it.
+// Verification is CPU bound: async callers should run this on a blocking thread.
+pub fn replay(shared: &SharedData, gameid: &str) -> Option<Vec<ReplayStep>> {
+    let (version, history) = {
+        let gmap = shared.games();
+        let game = gmap.get(gameid)?;
+        (game.version.clone(), game.history.clone())
+    };
+    Some(
+        history
+            .iter()
+            .map(|data| replay_step(&shared.methods, &version, data))
+            .collect(),
+    )
+}
+
+// Re-verify an accepted transaction and extract the public values of its journal
+fn replay_step(methods: &MethodRegistry, version: &str, data: &CommunicationData) -> ReplayStep {
+    let (verified, error) = match methods.verify(version, data.cmd, &data.receipt) {
+        Ok(()) => (true, None),
+        Err(err) => (false, Some(err)),
+    };
+    let mut step = ReplayStep {
+        cmd: data.cmd,
+        fleet: String::new(),
+        target: None,
+        pos: None,
+        report: None,
+        positions: Vec::new(),
+        reports: Vec::new(),
+        count: None,
+        team: None,
+        verified,
+        error,
+    };
+    let journal = &data.receipt.journal;
+    let decoded = match data.cmd {
+        Command::Fire => journal.decode::<FireJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.target = Some(j.target);
+            step.pos = Some(j.pos);
+        }),
+        Command::Report => journal.decode::<ReportJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.pos = Some(j.pos);
+            step.report = Some(j.report);
+        }),
+        Command::Salvo => journal.decode::<SalvoJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.target = Some(j.target);
+            step.positions = j.positions;
+        }),
+        Command::SalvoReport => journal.decode::<SalvoReportJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.positions = j.positions;
+            step.reports = j.reports;
+        }),
+        Command::ScanRequest => journal.decode::<ScanRequestJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.target = Some(j.target);
+            step.pos = Some(j.center);
+        }),
+        Command::Scan => journal.decode::<ScanJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.pos = Some(j.center);
+            step.count = Some(j.count);
+        }),
+        // Only the fleet of a move is public
+        Command::Move => journal
+            .decode::<MoveJournal>()
+            .map(|j| step.fleet = j.fleet),
+        // The cells left afloat, if shown, are in `positions`
+        Command::Resign => journal.decode::<ResignJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.positions = j.remaining.unwrap_or_default();
+        }),
+        Command::Join => journal.decode::<JoinJournal>().map(|j| {
+            step.fleet = j.fleet;
+            step.team = j.team;
+        }),
+        Command::Wave | Command::Win => journal
+            .decode::<BaseJournal>()
+            .map(|j| step.fleet = j.fleet),
+    };
+    if let Err(err) = decoded {
+        step.verified = false;
+        step.error = Some(format!("Invalid journal: {}", err));
+    }
+    step
+}
+
+// Check a game summary on its own: the receipt must come from the summary guest built into
+// this binary and compose receipts of a known guest version. Returns that version and the
+// summary journal. Verification is CPU bound, like replay.
+pub fn check_summary(
+    methods: &MethodRegistry,
+    summary: &GameSummary,
+) -> Result<(String, SummaryJournal), String> {
+    summary
+        .receipt
+        .verify(SUMMARY_ID)
+        .map_err(|err| format!("Could not verify summary receipt: {}", err))?;
+    let journal: SummaryJournal = summary
+        .receipt
+        .journal
+        .decode()
+        .map_err(|err| format!("Invalid journal: {}", err))?;
+    if journal.gameid != summary.gameid {
+        return Err(format!(
+            "The summary is of game {}, not {}",
+            journal.gameid, summary.gameid
+        ));
+    }
+    let version = methods
+        .version_of(&journal.image_ids)
+        .ok_or_else(|| "The summary composes receipts of unknown guests".to_string())?;
+    Ok((version.to_string(), journal))
+}
+
+// Publish the summary of a game, once checked against the transactions the chain accepted.
+// A summary must cover the whole history known at the time it is published.
+pub fn publish_summary(shared: &SharedData, summary: GameSummary) -> String {
+    match check_summary(&shared.methods, &summary) {
+        Ok((version, journal)) => record_summary(shared, summary, &version, journal),
+        Err(err) => err,
+    }
+}
+
+// Record a checked summary, composing receipts of guest `version` and proving `journal`
+fn record_summary(
+    shared: &SharedData,
+    summary: GameSummary,
+    version: &str,
+    journal: SummaryJournal,
+) -> String {
+    let mut gmap = shared.games();
+    let game = match gmap.get_mut(&summary.gameid) {
+        Some(game) => game,
+        None => return format!("Game {} not found", summary.gameid),
+    };
+    if version != game.version {
+        return format!(
+            "The summary composes receipts of guest version {}, the game uses {}",
+            version, game.version
+        );
+    }
+    if journal.transactions as usize != game.history.len() {
+        return format!(
+            "The summary covers {} transactions, the game has {}",
+            journal.transactions,
+            game.history.len()
+        );
+    }
+    let same_boards = journal.fleets.len() == game.pmap.len()
+        && journal.fleets.iter().all(|f| {
+            game.pmap
+                .get(&f.fleet)
+                .is_some_and(|p| p.current_state == f.board && p.shots_state == f.shots)
+        });
+    // A tournament game gives the first turn to the first fleet of its pairing, which the
+    // summary must have replayed the game with
+    if !same_boards
+        || journal.winner != game.winner
+        || journal.eliminated != game.eliminated
+        || journal.first_player != game.roster.first().cloned()
+    {
+        return "The summary does not match the state of the game".to_string();
+    }
+    game.summary = Some(summary);
+    shared.log(format!(
+        "Summary of {} transactions published for game {}",
+        journal.transactions, journal.gameid
+    ));
+
+    // The first summary proving a finished game rates its accounts and, in a tournament,
+    // decides its pairing for the fleet proven to have won
+    let tournament = game.tournament.clone();
+    if let Some(result) = game_result(&journal, &game.teams) {
+        let winner = journal
+            .winner
+            .clone()
+            .filter(|w| result.winners.contains(w));
+        if let Some(rated) = rated_result(&result, &game.pmap) {
+            let mut results = shared.results();
+            if !results.iter().any(|r| r.gameid == rated.gameid) {
+                shared.log(format!(
+                    "Game {} rated: accounts {} beat {}",
+                    rated.gameid,
+                    rated.winners.join(", "),
+                    rated.losers.join(", ")
+                ));
+                results.push(rated);
+            }
+        }
+        if let (Some(tournament), Some(winner)) = (tournament, winner) {
+            let mut tournaments = shared.tournaments();
+            if let Some(entry) = tournaments.get_mut(&tournament) {
+                if entry.record(&journal.gameid, &winner) {
+                    start_round(shared, &mut gmap, &tournament, entry);
+                }
+            }
+        }
+    }
+    "OK".to_string()
+}
+
+// Result of the game a summary proves, if it is finished: a fleet claimed victory and every
+// fleet outside its team was eliminated
+fn game_result(journal: &SummaryJournal, teams: &BTreeMap<String, String>) -> Option<GameResult> {
+    let winner = journal.winner.as_ref()?;
+    let (winners, losers): (Vec<String>, Vec<String>) = journal
+        .fleets
+        .iter()
+        .map(|f| f.fleet.clone())
+        .partition(|fleet| fleet == winner || teammates(teams, winner, fleet));
+    let decided = losers
+        .iter()
+        .all(|fleet| journal.eliminated.contains(fleet));
+    if !decided || losers.is_empty() || !winners.contains(winner) {
+        return None;
+    }
+    Some(GameResult {
+        gameid: journal.gameid.clone(),
+        winners,
+        losers,
+    })
+}
+
+// Result of a finished game between the accounts its fleets joined with. Fleet IDs are
+// chosen freely, so a game is only rated when every fleet proved an account at join and no
+// account played on both sides.
+fn rated_result(result: &GameResult, players: &HashMap<String, Player>) -> Option<GameResult> {
+    let accounts = |fleets: &[String]| -> Option<Vec<String>> {
+        let mut accounts = fleets
+            .iter()
+            .map(|fleet| players.get(fleet)?.account.clone())
+            .collect::<Option<Vec<String>>>()?;
+        accounts.sort();
+        accounts.dedup();
+        Some(accounts)
+    };
+    let (winners, losers) = (accounts(&result.winners)?, accounts(&result.losers)?);
+    if winners.iter().any(|account| losers.contains(account)) {
+        return None;
+    }
+    Some(GameResult {
+        gameid: result.gameid.clone(),
+        winners,
+        losers,
+    })
+}
+
+// Ratings of every account that played a rated game, best first
+pub fn leaderboard(shared: &SharedData) -> Vec<LeaderboardEntry> {
+    ratings::leaderboard(&shared.results())
+}
+
+// Open a tournament for registration, pinning its games to the current rules of the chain
+pub fn create_tournament(shared: &SharedData, request: TournamentRequest) -> String {
+    if request.tournament.trim().is_empty() {
+        return "A tournament needs an ID".to_string();
+    }
+    let mut tournaments = shared.tournaments();
+    if tournaments.contains_key(&request.tournament) {
+        return format!("Tournament {} already exists", request.tournament);
+    }
+    let rules = GameRules {
+        version: shared.methods.current().to_string(),
+        scan_limit: shared.scan_limit,
+        stake: shared.wagers.stake,
+    };
+    tournaments.insert(
+        request.tournament.clone(),
+        Tournament::new(request.format, rules),
+    );
+    shared.log(format!(
+        "Tournament {} opened for registration ({:?})",
+        request.tournament, request.format
+    ));
+    "OK".to_string()
+}
+
+// Register a fleet in a tournament whose first round has not started
+pub fn register_fleet(shared: &SharedData, tournament: &str, fleet: &str) -> String {
+    let mut tournaments = shared.tournaments();
+    let Some(entry) = tournaments.get_mut(tournament) else {
+        return format!("Tournament {} not found", tournament);
+    };
+    if entry.started() {
+        return format!("Tournament {} has started", tournament);
+    }
+    if fleet.trim().is_empty() {
+        return "A fleet needs an ID".to_string();
+    }
+    if entry.fleets.iter().any(|f| f == fleet) {
+        return format!(
+            "Fleet {} is already registered in tournament {}",
+            fleet, tournament
+        );
+    }
+    entry.fleets.push(fleet.to_string());
+    shared.log(format!(
+        "Fleet {} registered in tournament {}",
+        fleet, tournament
+    ));
+    "OK".to_string()
+}
+
+// Close the registration of a tournament and pair its first round
+pub fn start_tournament(shared: &SharedData, tournament: &str) -> String {
+    let mut gmap = shared.games();
+    let mut tournaments = shared.tournaments();
+    let Some(entry) = tournaments.get_mut(tournament) else {
+        return format!("Tournament {} not found", tournament);
+    };
+    if entry.started() {
+        return format!("Tournament {} has started", tournament);
+    }
+    if entry.fleets.len() < 2 {
+        return format!("Tournament {} needs at least 2 fleets", tournament);
+    }
+    start_round(shared, &mut gmap, tournament, entry);
+    "OK".to_string()
+}
+
+// Pair the next round of a tournament, reserving a game to each pairing, or crown its
+// champion if it is over
+fn start_round(
+    shared: &SharedData,
+    gmap: &mut HashMap<String, Game>,
+    name: &str,
+    tournament: &mut Tournament,
+) {
+    let Some(pairings) = tournament.next_round() else {
+        tournament.crown();
+        if let Some(champion) = &tournament.champion {
+            shared.log(format!("Tournament {} won by {}", name, champion));
+        }
+        return;
+    };
+    let round = tournament.rounds.len() + 1;
+    let mut paired = Vec::new();
+    let mut games = Vec::new();
+    for (idx, fleets) in pairings.into_iter().enumerate() {
+        if fleets.len() < 2 {
+            paired.push(format!("{} sits out", fleets[0]));
+            games.push(Pairing {
+                gameid: None,
+                winner: fleets.first().cloned(),
+                fleets,
+            });
+            continue;
+        }
+        // Game IDs already taken outside the tournament get a suffix
+        let base = format!("{}-r{}-g{}", name, round, idx + 1);
+        let mut gameid = base.clone();
+        let mut suffix = 1;
+        while gmap.contains_key(&gameid) {
+            suffix += 1;
+            gameid = format!("{}-{}", base, suffix);
+        }
+        gmap.insert(
+            gameid.clone(),
+            reserved_game(&tournament.rules, name, &fleets),
+        );
+        paired.push(format!("{} in game {}", fleets.join(" vs "), gameid));
+        games.push(Pairing {
+            gameid: Some(gameid),
+            fleets,
+            winner: None,
+        });
+    }
+    tournament.rounds.push(games);
+    shared.log(format!(
+        "Tournament {} round {}: {}",
+        name,
+        round,
+        paired.join(", ")
+    ));
+}
+
+// Decide a stalled game of the current round of a tournament once it has gone without a
+// transaction for the refund timeout. A fleet that never joined or is out of the game
+// forfeits, or else the fleet the game waits on; its opponent advances without a rating.
+pub fn forfeit(shared: &SharedData, tournament: &str, gameid: &str) -> String {
+    let mut gmap = shared.games();
+    let mut tournaments = shared.tournaments();
+    let Some(entry) = tournaments.get_mut(tournament) else {
+        return format!("Tournament {} not found", tournament);
+    };
+    let Some(pairing) = entry.pending(gameid) else {
+        return format!(
+            "Game {} is not undecided in the current round of tournament {}",
+            gameid, tournament
+        );
+    };
+    let Some(game) = gmap.get(gameid) else {
+        return format!("Game {} not found", gameid);
+    };
+    if let Some(winner) = &game.winner {
+        return format!(
+            "Game {} was won by {}, publish its summary to decide the pairing",
+            gameid, winner
+        );
+    }
+    let idle = game.updated.elapsed();
+    if idle < shared.wagers.refund_timeout {
+        return format!(
+            "Game {} can be forfeited in {} seconds",
+            gameid,
+            (shared.wagers.refund_timeout - idle).as_secs() + 1
+        );
+    }
+
+    let out: Vec<&String> = pairing
+        .fleets
+        .iter()
+        .filter(|fleet| !game.pmap.contains_key(*fleet) || game.eliminated.contains(fleet))
+        .collect();
+    let loser = match out.as_slice() {
+        [fleet] => Some(*fleet),
+        _ => game.next_report.as_ref().or(game.next_player.as_ref()),
+    }
+    .filter(|fleet| pairing.fleets.contains(fleet))
+    .or(pairing.fleets.last())
+    .cloned();
+    let (Some(loser), Some(winner)) = (
+        loser.clone(),
+        pairing
+            .fleets
+            .iter()
+            .find(|fleet| Some(*fleet) != loser.as_ref())
+            .cloned(),
+    ) else {
+        return format!("Game {} has no fleet to advance", gameid);
+    };
+
+    shared.log(format!(
+        "Fleet {} forfeited game {} of tournament {}, {} advances",
+        loser, gameid, tournament, winner
+    ));
+    if entry.record(gameid, &winner) {
+        start_round(shared, &mut gmap, tournament, entry);
+    }
+    "OK".to_string()
+}
+
+// Game of a tournament, taking only the fleets paired in it; the first of them fires first
+fn reserved_game(rules: &GameRules, tournament: &str, roster: &[String]) -> Game {
+    Game {
+        next_player: roster.first().cloned(),
+        tournament: Some(tournament.to_string()),
+        roster: roster.to_vec(),
+        ..Game::new(rules.clone())
+    }
+}
+
+// Reserve a game the way a tournament paired it, so that its transactions replay as they
+// were accepted; false if the game ID is taken
+pub fn reserve_game(
+    shared: &SharedData,
+    gameid: &str,
+    rules: GameRules,
+    tournament: &str,
+    roster: &[String],
+) -> bool {
+    let mut gmap = shared.games();
+    if gmap.contains_key(gameid) {
+        return false;
+    }
+    gmap.insert(
+        gameid.to_string(),
+        reserved_game(&rules, tournament, roster),
+    );
+    true
+}
+
+// State of a tournament
+pub fn tournament_status(shared: &SharedData, tournament: &str) -> Option<TournamentStatus> {
+    Some(shared.tournaments().get(tournament)?.status(tournament))
+}
+
+// State of every tournament, by ID
+pub fn tournaments(shared: &SharedData) -> Vec<TournamentStatus> {
+    shared
+        .tournaments()
+        .iter()
+        .map(|(name, tournament)| tournament.status(name))
+        .collect()
+}
+
+// Top an account up to the faucet amount
+pub fn faucet(shared: &SharedData, account: &str) -> String {
+    if !ledger::is_account(account) {
+        return format!("Invalid account {}", account);
+    }
+    let minted = shared.ledger().fund(account, shared.wagers.faucet);
+    shared.log(format!(
+        "Faucet gave {} tokens to account {}",
+        minted, account
+    ));
+    "OK".to_string()
+}
+
+// Tokens of an account, free and held by the games not settled yet
+pub fn account_status(shared: &SharedData, account: &str) -> AccountStatus {
+    let gmap = shared.games();
+    let escrow = gmap
+        .values()
+        .filter(|game| !game.settled)
+        .flat_map(|game| &game.escrow)
+        .filter(|e| e.account == account)
+        .map(|e| e.amount)
+        .sum();
+    AccountStatus {
+        account: account.to_string(),
+        balance: shared.ledger().balance(account),
+        escrow,
+    }
+}
+
+// Give every fleet its stake back once nobody can win a game any more: every fleet is
+// out of it, or it was abandoned without a transaction for the refund timeout. A game
+// with a winner is paid out instead.
+pub fn refund(shared: &SharedData, gameid: &str) -> String {
+    let mut gmap = shared.games();
+    let Some(game) = gmap.get_mut(gameid) else {
+        return format!("Game {} not found", gameid);
+    };
+    if game.settled || game.escrow.is_empty() {
+        return format!("Game {} holds no stakes", gameid);
+    }
+    if let Some(winner) = &game.winner {
+        return format!(
+            "Game {} was won by {}, its stakes are not refunded",
+            gameid, winner
+        );
+    }
+    let ended = game
+        .pmap
+        .keys()
+        .all(|fleet| game.eliminated.contains(fleet));
+    let idle = game.updated.elapsed();
+    if !ended && idle < shared.wagers.refund_timeout {
+        return format!(
+            "Game {} can be refunded in {} seconds",
+            gameid,
+            (shared.wagers.refund_timeout - idle).as_secs() + 1
+        );
+    }
+    shared.ledger().refund(&game.escrow);
+    game.settled = true;
+    shared.log(format!("Game {} refunded its stakes", gameid));
+    "OK".to_string()
+}
+
+// Latest published summary of a game
+pub fn summary(shared: &SharedData, gameid: &str) -> Option<GameSummary> {
+    shared.games().get(gameid)?.summary.clone()
+}
+
+fn xy_pos(pos: u8) -> String {
+    let x = pos % 10;
+    let y = pos / 10;
+    format!("{}{}", (x + 65) as char, y)
+}
+
+// A fleet publishes the board and salt it joined with. The secrets must open the
+// commitment made at join; every journal of the fleet is then replayed against them.
+pub fn reveal(shared: &SharedData, data: RevealData) -> String {
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the revealing player by fleet ID
+    let player = match game.pmap.get(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Revealing fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    if board_digest(&data.random, &data.board) != player.initial_state {
+        let msg = format!(
+            "Invalid reveal: board and salt do not match the commitment of fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+    if game.revealed.iter().any(|r| r.fleet == data.fleet) {
+        let msg = format!(
+            "Fleet {} already revealed its board in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    let mut issues = audit(&game.history, &data);
+    if let Err(reason) = check_fleet(&data.board) {
+        issues.insert(0, format!("Invalid fleet positioning: {}", reason));
+    }
+    let msg = if issues.is_empty() {
+        format!(
+            "Player {} revealed a board consistent with every report in game {}",
+            data.fleet, data.gameid
+        )
+    } else {
+        format!(
+            "Player {} revealed a board with {} inconsistencies in game {}",
+            data.fleet,
+            issues.len(),
+            data.gameid
+        )
+    };
+    shared.log(msg);
+    game.reveals.push(data.clone());
+    game.revealed.push(RevealedFleet {
+        fleet: data.fleet,
+        board: data.board,
+        issues,
+    });
+    "OK".to_string()
+}
+
+// Replay the journals of a fleet against its revealed board: each one must commit to the
+// board the fleet had at that point, and each report must match where the ships are.
+// The revealed moves, hidden until now, are applied at each move of the fleet.
+fn audit(history: &[CommunicationData], revealed: &RevealData) -> Vec<String> {
+    let (fleet, random) = (revealed.fleet.as_str(), revealed.random.as_str());
+    let mut issues = Vec::new();
+    let mut board = revealed.board.clone();
+    let mut hits = Vec::new();
+    let mut misses = Vec::new();
+    let mut moves = revealed.moves.iter();
+    for (idx, data) in history.iter().enumerate() {
+        let step = idx + 1;
+        let journal = &data.receipt.journal;
+        if data.cmd == Command::Move {
+            match journal.decode::<MoveJournal>() {
+                Ok(j) if j.fleet == fleet => {
+                    if j.board != board_digest(random, &board) {
+                        issues.push(format!(
+                            "Transaction {} ({:?}) commits to a different board",
+                            step, data.cmd
+                        ));
+                    }
+                    match moves.next().map(|m| moved_board(&board, &hits, &misses, m)) {
+                        Some(Ok(next_board)) => board = next_board,
+                        Some(Err(err)) => {
+                            issues.push(format!("Transaction {} is an illegal move: {}", step, err))
+                        }
+                        None => issues.push(format!(
+                            "Transaction {} moves a ship the reveal does not list",
+                            step
+                        )),
+                    }
+                    if j.next_board != board_digest(random, &board) {
+                        issues.push(format!(
+                            "Transaction {} leaves a different board than the revealed move",
+                            step
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        // (fleet, committed board) of every journal, plus the report details
+        let (author, committed, report) = match data.cmd {
+            Command::Fire => match journal.decode::<FireJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            Command::Report => match journal.decode::<ReportJournal>() {
+                Ok(j) => (
+                    j.fleet,
+                    j.board,
+                    Some((vec![(j.pos, j.report)], j.next_board)),
+                ),
+                Err(_) => continue,
+            },
+            Command::Salvo => match journal.decode::<SalvoJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            Command::ScanRequest => match journal.decode::<ScanRequestJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            Command::Scan => match journal.decode::<ScanJournal>() {
+                Ok(j) => {
+                    if j.fleet == fleet {
+                        let count = scan_area(j.center)
+                            .iter()
+                            .filter(|pos| board.contains(pos))
+                            .count();
+                        if count != j.count as usize {
+                            issues.push(format!(
+                                "Transaction {} counted {} ship cells around {}, but the revealed board gives {}",
+                                step,
+                                j.count,
+                                xy_pos(j.center),
+                                count
+                            ));
+                        }
+                    }
+                    (j.fleet, j.board, None)
+                }
+                Err(_) => continue,
+            },
+            Command::SalvoReport => match journal.decode::<SalvoReportJournal>() {
+                Ok(j) => {
+                    let reports = j.positions.into_iter().zip(j.reports).collect();
+                    (j.fleet, j.board, Some((reports, j.next_board)))
+                }
+                Err(_) => continue,
+            },
+            Command::Join => match journal.decode::<JoinJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            Command::Wave | Command::Win => match journal.decode::<BaseJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            Command::Resign => match journal.decode::<ResignJournal>() {
+                Ok(j) => (j.fleet, j.board, None),
+                Err(_) => continue,
+            },
+            // Handled above
+            Command::Move => continue,
+        };
+        if author != fleet {
+            continue;
+        }
+        if committed != board_digest(random, &board) {
+            issues.push(format!(
+                "Transaction {} ({:?}) commits to a different board",
+                step, data.cmd
+            ));
+        }
+        let Some((reports, next_board)) = report else {
+            continue;
+        };
+        for (pos, reported) in &reports {
+            let hit = board.contains(pos);
+            let expected = if hit { "Hit" } else { "Miss" };
+            if reported != expected {
+                issues.push(format!(
+                    "Transaction {} reported {} at {}, but the revealed board gives {}",
+                    step,
+                    reported,
+                    xy_pos(*pos),
+                    expected
+                ));
+            }
+            if hit {
+                board.retain(|p| p != pos);
+                hits.push(*pos);
+            } else {
+                misses.push(*pos);
+            }
+        }
+        if next_board != board_digest(random, &board) {
+            let positions: Vec<String> = reports.iter().map(|(pos, _)| xy_pos(*pos)).collect();
+            issues.push(format!(
+                "Transaction {} leaves a different board than the shot at {}",
+                step,
+                positions.join(", ")
+            ));
+        }
+    }
+    issues
+}
+
+// Reject a transaction whose receipt does not verify
+fn invalid_receipt(shared: &SharedData, action: &str) -> String {
+    shared.log(format!("Attempting to {} with invalid receipt", action));
+    "Could not verify receipt".to_string()
+}
+
+// Reject a transaction whose journal is not the one of the command's guest
+fn invalid_journal(shared: &SharedData, action: &str, err: impl std::fmt::Display) -> String {
+    shared.log(format!(
+        "Attempting to {} with an undecodable journal",
+        action
+    ));
+    format!("Invalid journal: {}", err)
+}
+
+fn handle_join(shared: &SharedData, input_data: &CommunicationData, version: &str) -> String {
+    let data: JoinJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "join game", err),
+    };
+    let mut gmap = shared.games();
+    // A new game is pinned to the version the first join verified against
+    let version = version.to_string();
+
+    // A game paired in a tournament only takes the fleets of its pairing, without teams
+    if let Some((game, tournament)) = gmap
+        .get(&data.gameid)
+        .and_then(|game| Some((game, game.tournament.as_ref()?)))
+    {
+        let refusal = if !game.roster.contains(&data.fleet) {
+            Some(format!(
+                "Fleet {} is not paired in game {} of tournament {}",
+                data.fleet, data.gameid, tournament
+            ))
+        } else if data.team.is_some() {
+            Some(format!(
+                "Game {} of tournament {} takes no teams",
+                data.gameid, tournament
+            ))
+        } else {
+            None
+        };
+        if let Some(msg) = refusal {
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+
+    // A fleet joining a game with stakes deposits its stake from the account its join
+    // receipt proves it holds the key of
+    let (stake, joined, settled) = match gmap.get(&data.gameid) {
+        Some(game) => (
+            game.stake,
+            game.pmap.contains_key(&data.fleet),
+            game.settled,
+        ),
+        None => (shared.wagers.stake, false, false),
+    };
+    if stake > 0 && !joined {
+        let deposit = match &data.account {
+            _ if settled => Err(format!("Game {} is settled", data.gameid)),
+            Some(account) => shared.ledger().debit(account, stake),
+            None => Err(format!(
+                "Game {} takes a stake of {} tokens: join with an account",
+                data.gameid, stake
+            )),
+        };
+        if let Err(msg) = deposit {
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+    let game = gmap.entry(data.gameid.clone()).or_insert_with(|| Game {
+        next_player: Some(data.fleet.clone()),
+        ..Game::new(GameRules {
+            version,
+            scan_limit: shared.scan_limit,
+            stake,
+        })
+    });
+    let player_inserted = !game.pmap.contains_key(&data.fleet);
+    if player_inserted {
+        game.pmap.insert(
+            data.fleet.clone(),
+            Player {
+                current_state: data.board,
+                initial_state: data.board,
+                shots_state: shots_digest(&[]),
+                account: data.account.clone(),
+            },
+        );
+        if let Some(team) = &data.team {
+            game.teams.insert(data.fleet.clone(), team.clone());
+        }
+        if let (true, Some(account)) = (stake > 0, &data.account) {
+            game.escrow.push(Escrow {
+                fleet: data.fleet.clone(),
+                account: account.clone(),
+                amount: stake,
+            });
+        }
+        game.history.push(input_data.clone());
+    }
+    let mesg = if player_inserted {
+        match &data.team {
+            Some(team) => format!(
+                "Player {} joined game {} in team {}",
+                data.fleet, data.gameid, team
+            ),
+            None => format!("Player {} joined game {}", data.fleet, data.gameid),
+        }
+    } else {
+        format!("Player already in game {}", data.gameid)
+    };
+    shared.log(mesg);
+    "OK".to_string()
+}
+
+fn handle_fire(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the FireJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: FireJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "fire", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // A salvo game takes no single shots
+    if game.mode == Some(GameMode::Salvo) {
+        let msg = format!("Game {} is played with salvos", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Find the firing player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Firing fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Enforce turn order; the fleets of a team share their turn
+    match &game.next_player {
+        Some(expected_player) => {
+            if expected_player != &data.fleet
+                && !teammates(&game.teams, expected_player, &data.fleet)
+            {
+                let msg = format!(
+                    "It's not {}'s turn to fire in game {}. It's {}'s turn.",
+                    data.fleet, data.gameid, expected_player
+                );
+                shared.log(msg.clone());
+                return msg;
+            }
+        }
+        None => {
+            let msg = format!(
+                "No player is allowed to fire right now in game {}. Awaiting report.",
+                data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+
+    // Ensure the current state matches the board hash in the fire request
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid fire: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Ensure the shot history the guest proved the shot new against is the fleet's
+    if player.shots_state != data.shots {
+        let msg = format!(
+            "Invalid fire: shot history does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    if !game.pmap.contains_key(&data.target) {
+        let msg = format!(
+            "Target fleet {} not found in game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Fleets of a team cannot fire at each other
+    if teammates(&game.teams, &data.fleet, &data.target) {
+        let msg = format!(
+            "Fleet {} cannot fire at its teammate {} in game {}",
+            data.fleet, data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // A sunk or resigned fleet never reports again, so it cannot be targeted
+    if game.eliminated.contains(&data.target) {
+        let msg = format!(
+            "Target fleet {} is out of game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    if let Some(player) = game.pmap.get_mut(&data.fleet) {
+        player.shots_state = data.next_shots;
+    }
+
+    // Set up for the next report
+    game.next_report = Some(data.target.clone());
+    game.next_player = None;
+    game.last_player = Some(data.fleet.clone());
+
+    // Broadcast the result of the fire action
+    let pos = xy_pos(data.pos);
+    let msg = format!(
+        "Player {} fired at {}'s fleet at position {} in game {}",
+        data.fleet, data.target, pos, data.gameid
+    );
+    shared.log(msg.clone());
+
+    game.shots.push(ShotRecord {
+        fleet: data.fleet.clone(),
+        target: data.target.clone(),
+        pos: data.pos,
+        report: None,
+    });
+    game.last_shot_pos = Some(data.pos); //Store the last shot position
+    game.mode = Some(GameMode::Classic);
+    game.history.push(input_data.clone());
+    // Return success
+    "OK".to_string()
+}
+
+fn handle_report(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the ReportJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: ReportJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "report", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // A scan is answered with the scan guest, not a report
+    if game.scans.iter().any(|s| s.count.is_none()) {
+        let msg = format!("A scan is awaiting its answer in game {}", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // The shots of a salvo are answered together
+    if game.mode == Some(GameMode::Salvo) {
+        let msg = format!(
+            "Game {} is played with salvos, report the whole salvo",
+            data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Find the reporting player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Reporting fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Enforce report order
+    if let Some(expected_reporter) = &game.next_report {
+        if expected_reporter != &data.fleet {
+            let msg = format!(
+                "It's not {}'s turn to report in game {}. It's {}'s turn.",
+                data.fleet, data.gameid, expected_reporter
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    } else {
+        let msg = format!("No report expected at this time in game {}.", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+    // Check if the last shot position is the same as the one being reported
+    if let Some(last_pos) = game.last_shot_pos {
+        if last_pos != data.pos {
+            let msg = format!(
+                "Invalid report: last shot position {} does not match reported position {} for fleet {} in game {}",
+                xy_pos(last_pos), xy_pos(data.pos), data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    } else {
+        let msg = format!(
+            "Invalid report: no last shot position recorded for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // CHECK: Ensure the current state matches the board hash in the report
+    // This check ensures that the player is reporting based on the correct board state.
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid report: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Update the player's state with the new board hash (next_board)
+    player.current_state = data.next_board;
+
+    // Record the result on the shot being answered
+    if let Some(shot) = game.shots.last_mut() {
+        shot.report = Some(data.report.clone());
+    }
+
+    // After a valid report, set the next player to the reporter
+    game.next_player = Some(data.fleet.clone());
+    game.next_report = None;
+
+    // Broadcast the result of the report action
+    let pos = xy_pos(data.pos);
+    let msg = format!(
+        "Player {} reported result '{}' at position {} in game {}",
+        data.fleet, data.report, pos, data.gameid
+    );
+    shared.log(msg.clone());
+
+    // A fleet is out once every one of its cells has been reported hit
+    let hits = game
+        .shots
+        .iter()
+        .filter(|s| s.target == data.fleet && s.report.as_deref() == Some("Hit"))
+        .count();
+    if hits >= FLEET_CELLS && !game.eliminated.contains(&data.fleet) {
+        game.eliminated.push(data.fleet.clone());
+        let msg = format!(
+            "Fleet {} was eliminated from game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg);
+    }
+    game.history.push(input_data.clone());
+
+    // Return success
+    "OK".to_string()
+}
+
+fn handle_salvo(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the SalvoJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: SalvoJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "fire a salvo", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // The first shot of a game decides its mode
+    if game.mode == Some(GameMode::Classic) {
+        let msg = format!("Game {} is played one shot per turn", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+    // Salvos are sized by the ships of the joined board, which moved ships no longer match
+    if game.history.iter().any(|h| h.cmd == Command::Move) {
+        let msg = format!(
+            "Game {} has moved ships and cannot fire salvos",
+            data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Find the firing player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Firing fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Enforce turn order; the fleets of a team share their turn
+    match &game.next_player {
+        Some(expected_player) => {
+            if expected_player != &data.fleet
+                && !teammates(&game.teams, expected_player, &data.fleet)
+            {
+                let msg = format!(
+                    "It's not {}'s turn to fire in game {}. It's {}'s turn.",
+                    data.fleet, data.gameid, expected_player
+                );
+                shared.log(msg.clone());
+                return msg;
+            }
+        }
+        None => {
+            let msg = format!(
+                "No player is allowed to fire right now in game {}. Awaiting report.",
+                data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+
+    // The ships afloat are counted on the fleet joined with, so it must be the committed one
+    if player.initial_state != data.initial {
+        let msg = format!(
+            "Invalid salvo: joined board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Ensure the current state matches the board hash in the salvo
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid salvo: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Ensure the shot history the guest proved the shots new against is the fleet's
+    if player.shots_state != data.shots {
+        let msg = format!(
+            "Invalid salvo: shot history does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    if !game.pmap.contains_key(&data.target) {
+        let msg = format!(
+            "Target fleet {} not found in game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Fleets of a team cannot fire at each other
+    if teammates(&game.teams, &data.fleet, &data.target) {
+        let msg = format!(
+            "Fleet {} cannot fire at its teammate {} in game {}",
+            data.fleet, data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // A sunk or resigned fleet never reports again, so it cannot be targeted
+    if game.eliminated.contains(&data.target) {
+        let msg = format!(
+            "Target fleet {} is out of game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    if let Some(player) = game.pmap.get_mut(&data.fleet) {
+        player.shots_state = data.next_shots;
+    }
+
+    // Set up for the report of the whole salvo
+    game.mode = Some(GameMode::Salvo);
+    game.next_report = Some(data.target.clone());
+    game.next_player = None;
+    game.last_player = Some(data.fleet.clone());
+    game.last_shot_pos = data.positions.last().copied();
+
+    let positions: Vec<String> = data.positions.iter().map(|&pos| xy_pos(pos)).collect();
+    let msg = format!(
+        "Player {} fired a salvo at {}'s fleet at positions {} in game {}",
+        data.fleet,
+        data.target,
+        positions.join(", "),
+        data.gameid
+    );
+    shared.log(msg);
+
+    for &pos in &data.positions {
+        game.shots.push(ShotRecord {
+            fleet: data.fleet.clone(),
+            target: data.target.clone(),
+            pos,
+            report: None,
+        });
+    }
+    game.history.push(input_data.clone());
+    "OK".to_string()
+}
+
+fn handle_salvo_report(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the SalvoReportJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: SalvoReportJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "report a salvo", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    if game.mode != Some(GameMode::Salvo) {
+        let msg = format!("No salvo was fired in game {}", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+    if game.scans.iter().any(|s| s.count.is_none()) {
+        let msg = format!("A scan is awaiting its answer in game {}", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Find the reporting player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Reporting fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Enforce report order
+    match &game.next_report {
+        Some(expected_reporter) => {
+            if expected_reporter != &data.fleet {
+                let msg = format!(
+                    "It's not {}'s turn to report in game {}. It's {}'s turn.",
+                    data.fleet, data.gameid, expected_reporter
+                );
+                shared.log(msg.clone());
+                return msg;
+            }
+        }
+        None => {
+            let msg = format!("No report expected at this time in game {}.", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+
+    // The report must answer every shot of the salvo, in the order they were fired
+    let pending: Vec<u8> = game
+        .shots
+        .iter()
+        .filter(|s| s.target == data.fleet && s.report.is_none())
+        .map(|s| s.pos)
+        .collect();
+    if pending != data.positions || data.reports.len() != data.positions.len() {
+        let msg = format!(
+            "Invalid report: positions do not match the salvo fired at fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Ensure the current state matches the board hash in the report
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid report: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+    player.current_state = data.next_board;
+
+    // Record the result on each shot being answered
+    let mut reports = data.reports.iter();
+    for shot in game
+        .shots
+        .iter_mut()
+        .filter(|s| s.target == data.fleet && s.report.is_none())
+    {
+        shot.report = reports.next().cloned();
+    }
+
+    // After a valid report, set the next player to the reporter
+    game.next_player = Some(data.fleet.clone());
+    game.next_report = None;
+
+    let results: Vec<String> = data
+        .positions
+        .iter()
+        .zip(&data.reports)
+        .map(|(&pos, report)| format!("{} at {}", report, xy_pos(pos)))
+        .collect();
+    let msg = format!(
+        "Player {} reported {} in game {}",
+        data.fleet,
+        results.join(", "),
+        data.gameid
+    );
+    shared.log(msg);
+
+    // A fleet is out once every one of its cells has been reported hit
+    let hits = game
+        .shots
+        .iter()
+        .filter(|s| s.target == data.fleet && s.report.as_deref() == Some("Hit"))
+        .count();
+    if hits >= FLEET_CELLS && !game.eliminated.contains(&data.fleet) {
+        game.eliminated.push(data.fleet.clone());
+        let msg = format!(
+            "Fleet {} was eliminated from game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg);
+    }
+    game.history.push(input_data.clone());
+
+    "OK".to_string()
+}
+
+fn handle_scan_request(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the ScanRequestJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: ScanRequestJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "request a scan", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the scanning player by fleet ID
+    let player = match game.pmap.get(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Scanning fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // A scan takes the turn of the fleet, like a shot
+    match &game.next_player {
+        Some(expected_player) => {
+            if expected_player != &data.fleet
+                && !teammates(&game.teams, expected_player, &data.fleet)
+            {
+                let msg = format!(
+                    "It's not {}'s turn to scan in game {}. It's {}'s turn.",
+                    data.fleet, data.gameid, expected_player
+                );
+                shared.log(msg.clone());
+                return msg;
+            }
+        }
+        None => {
+            let msg = format!(
+                "No player is allowed to scan right now in game {}. Awaiting report.",
+                data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    }
+
+    // Ensure the current state matches the board hash in the request
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid scan: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    if !game.pmap.contains_key(&data.target) {
+        let msg = format!(
+            "Target fleet {} not found in game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Fleets of a team cannot scan each other
+    if teammates(&game.teams, &data.fleet, &data.target) {
+        let msg = format!(
+            "Fleet {} cannot scan its teammate {} in game {}",
+            data.fleet, data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // A sunk or resigned fleet never answers again, so it cannot be scanned
+    if game.eliminated.contains(&data.target) {
+        let msg = format!(
+            "Target fleet {} is out of game {}",
+            data.target, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    let used = game.scans.iter().filter(|s| s.fleet == data.fleet).count();
+    if used >= game.scan_limit as usize {
+        let msg = format!(
+            "Fleet {} has no scan left in game {} (limit {})",
+            data.fleet, data.gameid, game.scan_limit
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Set up for the answer of the target
+    game.next_report = Some(data.target.clone());
+    game.next_player = None;
+    game.last_player = Some(data.fleet.clone());
+
+    let msg = format!(
+        "Player {} scanned {}'s fleet around {} in game {}",
+        data.fleet,
+        data.target,
+        xy_pos(data.center),
+        data.gameid
+    );
+    shared.log(msg);
+
+    game.scans.push(ScanRecord {
+        fleet: data.fleet,
+        target: data.target,
+        center: data.center,
+        count: None,
+    });
+    game.history.push(input_data.clone());
+    "OK".to_string()
+}
+
+fn handle_scan(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the ScanJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: ScanJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "answer a scan", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the scanned player by fleet ID
+    let player = match game.pmap.get(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Scanned fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // The answer must be to the scan awaiting it, around the requested center
+    let Some(scan) = game
+        .scans
+        .iter_mut()
+        .find(|s| s.target == data.fleet && s.count.is_none())
+    else {
+        let msg = format!(
+            "No scan of fleet {} awaits an answer in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    };
+    if scan.center != data.center {
+        let msg = format!(
+            "Invalid scan: the scan was requested around {}, not {}",
+            xy_pos(scan.center),
+            xy_pos(data.center)
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Ensure the count was proven on the board the fleet is committed to
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid scan: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+    scan.count = Some(data.count);
+
+    // After a valid answer, the scanned fleet plays next, as after a report
+    game.next_player = Some(data.fleet.clone());
+    game.next_report = None;
+
+    let msg = format!(
+        "Player {} has {} ship cells around {} in game {}",
+        data.fleet,
+        data.count,
+        xy_pos(data.center),
+        data.gameid
+    );
+    shared.log(msg);
+    game.history.push(input_data.clone());
+    "OK".to_string()
+}
+
+fn handle_wave(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the BaseJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: BaseJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "wave", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the reporting player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Reporting fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // This check ensures that the player is reporting based on the correct board state.
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid report: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+    // Enforce turn order
+    if let Some(expected_player) = &game.next_player {
+        if expected_player != &data.fleet && !teammates(&game.teams, expected_player, &data.fleet) {
+            let msg = format!(
+                "It's not {}'s turn to wave in game {}. It's {}'s turn.",
+                data.fleet, data.gameid, expected_player
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    } else {
+        let msg = format!(
+            "No player is allowed to wave right now in game {}. Awaiting report.",
+            data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Broadcast the wave action
+    let msg = format!(
+        "Player {} waved their turn on game {}",
+        data.fleet, data.gameid
+    );
+    shared.log(msg.clone());
+
+    // If the player waves their turn, we need to set the next player which is the last player
+    if let Some(last_player) = &game.last_player {
+        game.next_player = Some(last_player.clone());
+    } else {
+        // If no last player, we can't set the next player
+        let msg = format!(
+            "No last player found to set next player in game {}",
+            data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Set last player to current player
+    game.last_player = Some(data.fleet.clone());
+    game.history.push(input_data.clone());
+    // Return success
+    "OK".to_string()
+}
+
+fn handle_move(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the MoveJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: MoveJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "move", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    if game.mode == Some(GameMode::Salvo) {
+        let msg = format!("Ships cannot move in salvo game {}", data.gameid);
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Enforce turn order: a move takes the place of a shot
+    let on_turn = game.next_player.as_ref().is_some_and(|expected_player| {
+        expected_player == &data.fleet || teammates(&game.teams, expected_player, &data.fleet)
+    });
+    if !on_turn {
+        let msg = format!(
+            "It's not {}'s turn to move in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // The guest only let undamaged ships move, and never onto a cell shot at, given these
+    // hits and misses, so they must be the ones reported on the fleet
+    let reported = |report: &str| {
+        let mut cells: Vec<u8> = game
+            .shots
+            .iter()
+            .filter(|s| s.target == data.fleet && s.report.as_deref() == Some(report))
+            .map(|s| s.pos)
+            .collect();
+        cells.sort_unstable();
+        cells
+    };
+    if reported("Hit") != data.hits || reported("Miss") != data.misses {
+        let msg = format!(
+            "Invalid move: hits and misses do not match the reports on fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Find the moving player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Moving fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // This check ensures that the ship moves on the correct board state.
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid move: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // The turn goes on as after a wave, back to the fleet that played before
+    let Some(last_player) = game.last_player.clone() else {
+        let msg = format!(
+            "No last player found to set next player in game {}",
+            data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    };
+    player.current_state = data.next_board;
+    game.next_player = Some(last_player);
+    game.last_player = Some(data.fleet.clone());
+
+    let msg = format!("Player {} moved a ship in game {}", data.fleet, data.gameid);
+    shared.log(msg);
+
+    game.history.push(input_data.clone());
+    "OK".to_string()
+}
+
+fn handle_resign(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the ResignJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: ResignJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "resign", err),
+    };
+
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the resigning player by fleet ID
+    let player = match game.pmap.get(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Resigning fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // The cells shown, if any, must be those of the fleet's current board
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid resign: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // A fleet may resign at any time, even while the game waits on it
+    game.resigned.push(ResignRecord {
+        fleet: data.fleet.clone(),
+        remaining: data.remaining.clone(),
+    });
+    if !game.eliminated.contains(&data.fleet) {
+        game.eliminated.push(data.fleet.clone());
+    }
+
+    // The turn goes to the fleet that played before if it is still in the game, or else
+    // to the first fleet by name that is
+    let in_game = |fleet: &String| !game.eliminated.contains(fleet);
+    let successor = match game.last_player.clone().filter(in_game) {
+        Some(fleet) => Some(fleet),
+        None => {
+            let mut fleets: Vec<&String> = game.pmap.keys().filter(|f| in_game(f)).collect();
+            fleets.sort();
+            fleets.first().map(|fleet| fleet.to_string())
+        }
+    };
+    let waiting = Some(&data.fleet);
+    if game.next_player.as_ref() == waiting || game.next_report.as_ref() == waiting {
+        game.next_player = successor.clone();
+        game.next_report = None;
+        // Drop the scan it had to answer, since no report is taken while a scan is open
+        game.scans
+            .retain(|s| s.target != data.fleet || s.count.is_some());
+    }
+    if game.last_player.as_ref() == waiting {
+        game.last_player = successor;
+    }
+
+    let msg = match &data.remaining {
+        Some(remaining) => format!(
+            "Player {} resigned from game {} with {} cells afloat",
+            data.fleet,
+            data.gameid,
+            remaining.len()
+        ),
+        None => format!("Player {} resigned from game {}", data.fleet, data.gameid),
+    };
+    shared.log(msg);
+
+    game.history.push(input_data.clone());
+    "OK".to_string()
+}
+
+fn handle_win(shared: &SharedData, input_data: &CommunicationData) -> String {
+    // Decode the BaseJournal from the receipt; it is only trusted once the receipt is
+    // verified against the guests of the game below
+    let data: BaseJournal = match input_data.receipt.journal.decode() {
+        Ok(data) => data,
+        Err(err) => return invalid_journal(shared, "claim win", err),
+    };
+    // Lock the game map to update the state
+    let mut gmap = shared.games();
+
+    // Find the game by game ID
+    let game = match gmap.get_mut(&data.gameid) {
+        Some(game) => game,
+        None => {
+            let msg = format!("Game {} not found", data.gameid);
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // Find the reporting player by fleet ID
+    let player = match game.pmap.get_mut(&data.fleet) {
+        Some(player) => player,
+        None => {
+            let msg = format!(
+                "Reporting fleet {} not found in game {}",
+                data.fleet, data.gameid
+            );
+            shared.log(msg.clone());
+            return msg;
+        }
+    };
+
+    // This check ensures that the player is reporting based on the correct board state.
+    if player.current_state != data.board {
+        let msg = format!(
+            "Invalid report: board hash does not match stored state for fleet {} in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // A fleet wins, with its team if it has one, once every fleet outside the team is
+    // eliminated; a game without an opponent has nobody to win against
+    let opponents: Vec<&String> = game
+        .pmap
+        .keys()
+        .filter(|fleet| *fleet != &data.fleet && !teammates(&game.teams, &data.fleet, fleet))
+        .collect();
+    if opponents.is_empty() {
+        let msg = format!(
+            "Invalid win claim: fleet {} has no opponent in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+    if opponents
+        .iter()
+        .any(|fleet| !game.eliminated.contains(fleet))
+    {
+        let msg = format!(
+            "Invalid win claim: fleet {} has opponents afloat in game {}",
+            data.fleet, data.gameid
+        );
+        shared.log(msg.clone());
+        return msg;
+    }
+
+    // Broadcast the win claim
+    let msg = match game.teams.get(&data.fleet) {
+        Some(team) => format!(
+            "Player {} claims victory for team {} in game {}!",
+            data.fleet, team, data.gameid
+        ),
+        None => format!(
+            "Player {} claims victory in game {}!",
+            data.fleet, data.gameid
+        ),
+    };
+    shared.log(msg.clone());
+    let winner = game
+        .winner
+        .get_or_insert_with(|| data.fleet.clone())
+        .clone();
+    game.history.push(input_data.clone());
+    payout(shared, game, &data.gameid, &winner);
+
+    "OK".to_string()
+}
+
+// Pay the stakes of a game to the side of `winner` once every fleet outside it is
+// eliminated: the pot is split evenly between the accounts of the side, the claimer
+// taking what cannot be split
+fn payout(shared: &SharedData, game: &mut Game, gameid: &str, winner: &str) {
+    if game.settled || game.escrow.is_empty() {
+        return;
+    }
+    let decided = game.pmap.keys().all(|fleet| {
+        fleet == winner || teammates(&game.teams, winner, fleet) || game.eliminated.contains(fleet)
+    });
+    if !decided {
+        return;
+    }
+    let mut accounts: Vec<String> = game
+        .escrow
+        .iter()
+        .filter(|e| e.fleet != winner && teammates(&game.teams, winner, &e.fleet))
+        .map(|e| e.account.clone())
+        .collect();
+    if let Some(claimer) = game.escrow.iter().find(|e| e.fleet == winner) {
+        accounts.insert(0, claimer.account.clone());
+    }
+    let pot = shared.ledger().pay(&game.escrow, &accounts);
+    game.settled = true;
+    shared.log(format!(
+        "Game {} paid {} tokens to the side of {}",
+        gameid, pot, winner
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fleetcore::TournamentFormat;
+
+    fn fleets(names: &[&str]) -> Vec<String> {
+        names.iter().map(|f| f.to_string()).collect()
+    }
+
+    fn player(account: Option<&str>) -> Player {
+        Player {
+            current_state: Digest::default(),
+            initial_state: Digest::default(),
+            shots_state: shots_digest(&[]),
+            account: account.map(str::to_string),
+        }
+    }
+
+    // Receipt carrying `journal`, standing for a proof the chain already verified
+    fn receipt<T: serde::Serialize>(journal: &T) -> risc0_zkvm::Receipt {
+        let words = risc0_zkvm::serde::to_vec(journal).unwrap();
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let claim = risc0_zkvm::ReceiptClaim::ok(Digest::ZERO, bytes.clone());
+        let inner = risc0_zkvm::InnerReceipt::Fake(risc0_zkvm::FakeReceipt::new(claim));
+        risc0_zkvm::Receipt::new(inner, bytes)
+    }
+
+    fn send<T: serde::Serialize>(shared: &SharedData, cmd: Command, journal: &T) -> String {
+        let data = CommunicationData {
+            cmd,
+            receipt: receipt(journal),
+        };
+        let (gameid, fleet) = sender(&data).unwrap();
+        let verified = Verified {
+            gameid,
+            fleet,
+            version: shared.methods.current().to_string(),
+        };
+        apply(shared, &data, &verified)
+    }
+
+    #[test]
+    fn reserved_games_replay_from_the_first_fleet_of_the_pairing() {
+        let shared = tournament(TournamentFormat::Bracket, &["a", "b", "c"]);
+        let gameid = "cup-r1-g1".to_string();
+        assert_eq!(shared.games()[&gameid].roster, fleets(&["a", "b"]));
+
+        // b joins first, yet a fires first as its pairing put it first
+        let board = |fleet: &str| Digest::from([fleet.as_bytes()[0] as u32; 8]);
+        for fleet in ["b", "a"] {
+            let join = JoinJournal {
+                gameid: gameid.clone(),
+                fleet: fleet.to_string(),
+                board: board(fleet),
+                ..Default::default()
+            };
+            assert_eq!(send(&shared, Command::Join, &join), "OK");
+        }
+        let fire = |fleet: &str, target: &str| FireJournal {
+            gameid: gameid.clone(),
+            fleet: fleet.to_string(),
+            board: board(fleet),
+            target: target.to_string(),
+            pos: 0,
+            shots: shots_digest(&[]),
+            next_shots: shots_digest(&[fleetcore::Shot {
+                target: target.to_string(),
+                pos: 0,
+            }]),
+        };
+        assert_ne!(send(&shared, Command::Fire, &fire("b", "a")), "OK");
+        assert_eq!(send(&shared, Command::Fire, &fire("a", "b")), "OK");
+        let resign = ResignJournal {
+            gameid: gameid.clone(),
+            fleet: "b".to_string(),
+            board: board("b"),
+            remaining: None,
+        };
+        assert_eq!(send(&shared, Command::Resign, &resign), "OK");
+        let win = BaseJournal {
+            gameid: gameid.clone(),
+            fleet: "a".to_string(),
+            board: board("a"),
+        };
+        assert_eq!(send(&shared, Command::Win, &win), "OK");
+
+        let journal = |first_player: &str| {
+            let gmap = shared.games();
+            let game = &gmap[&gameid];
+            SummaryJournal {
+                gameid: gameid.clone(),
+                image_ids: Vec::new(),
+                transactions: game.history.len() as u32,
+                fleets: game
+                    .pmap
+                    .iter()
+                    .map(|(fleet, p)| fleetcore::FleetSummary {
+                        fleet: fleet.clone(),
+                        board: p.current_state,
+                        shots: p.shots_state,
+                        hits: 0,
+                    })
+                    .collect(),
+                eliminated: game.eliminated.clone(),
+                winner: game.winner.clone(),
+                first_player: Some(first_player.to_string()),
+            }
+        };
+        let publish = |journal: SummaryJournal| {
+            let summary = GameSummary {
+                gameid: gameid.clone(),
+                receipt: receipt(&journal),
+            };
+            let version = shared.methods.current().to_string();
+            record_summary(&shared, summary, &version, journal)
+        };
+        // A summary replaying the game from the first fleet to join does not match it
+        assert_ne!(publish(journal("b")), "OK");
+        assert_eq!(publish(journal("a")), "OK");
+
+        let tournaments = shared.tournaments();
+        let rounds = &tournaments["cup"].rounds;
+        assert_eq!(rounds[0][0].winner.as_deref(), Some("a"));
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[1][0].fleets, fleets(&["a", "c"]));
+        assert!(shared.games().contains_key("cup-r2-g1"));
+    }
+
+    // Tournament "cup" between `names`, its first round started on a chain letting stalled
+    // games be forfeited at once
+    fn tournament(format: TournamentFormat, names: &[&str]) -> SharedData {
+        let wagers = Wagers {
+            refund_timeout: std::time::Duration::ZERO,
+            ..Wagers::default()
+        };
+        let shared = SharedData::new(broadcast::channel(16).0, MethodRegistry::builtin())
+            .with_wagers(wagers);
+        let request = TournamentRequest {
+            tournament: "cup".to_string(),
+            format,
+        };
+        assert_eq!(create_tournament(&shared, request), "OK");
+        for fleet in names {
+            assert_eq!(register_fleet(&shared, "cup", fleet), "OK");
+        }
+        assert_eq!(start_tournament(&shared, "cup"), "OK");
+        shared
+    }
+
+    // Decide a game of the tournament for `winner`: its opponent never joins and forfeits
+    fn advance(shared: &SharedData, gameid: &str, winner: &str) {
+        let join = JoinJournal {
+            gameid: gameid.to_string(),
+            fleet: winner.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(send(shared, Command::Join, &join), "OK");
+        assert_eq!(forfeit(shared, "cup", gameid), "OK");
+    }
+
+    // Fleets of each pairing of a round of the tournament
+    fn round(shared: &SharedData, round: usize) -> Vec<Vec<String>> {
+        shared.tournaments()["cup"].rounds[round]
+            .iter()
+            .map(|p| p.fleets.clone())
+            .collect()
+    }
+
+    #[test]
+    fn bracket_rotates_the_byes_of_odd_fields() {
+        let shared = tournament(TournamentFormat::Bracket, &["a", "b", "c", "d", "e"]);
+        assert_eq!(
+            round(&shared, 0),
+            [fleets(&["a", "b"]), fleets(&["c", "d"]), fleets(&["e"])]
+        );
+        assert_eq!(
+            shared.tournaments()["cup"].rounds[0][2].winner.as_deref(),
+            Some("e")
+        );
+
+        // e sat the first round out, so d sits out the second
+        advance(&shared, "cup-r1-g1", "a");
+        advance(&shared, "cup-r1-g2", "d");
+        assert_eq!(round(&shared, 1), [fleets(&["a", "e"]), fleets(&["d"])]);
+
+        advance(&shared, "cup-r2-g1", "e");
+        assert_eq!(round(&shared, 2), [fleets(&["e", "d"])]);
+        advance(&shared, "cup-r3-g1", "d");
+        let tournaments = shared.tournaments();
+        assert_eq!(tournaments["cup"].rounds.len(), 3);
+        assert_eq!(tournaments["cup"].champion.as_deref(), Some("d"));
+    }
+
+    #[test]
+    fn record_takes_only_a_fleet_of_the_pairing() {
+        let shared = tournament(TournamentFormat::Bracket, &["a", "b", "c", "d"]);
+        let mut tournaments = shared.tournaments();
+        let t = tournaments.get_mut("cup").unwrap();
+        assert!(!t.record("cup-r1-g1", "c"));
+        assert!(!t.record("cup-r9-g1", "a"));
+        assert!(t.pending("cup-r1-g1").is_some());
+
+        assert!(!t.record("cup-r1-g1", "b"));
+        assert!(t.pending("cup-r1-g1").is_none());
+        assert!(!t.record("cup-r1-g1", "a"));
+        assert_eq!(t.rounds[0][0].winner.as_deref(), Some("b"));
+    }
+
+    // Play every round of a round robin, `winner` deciding each game from its fleets
+    fn play_round_robin(shared: &SharedData, winner: impl Fn(&[String]) -> String) {
+        while shared.tournaments()["cup"].champion.is_none() {
+            let games: Vec<(String, String)> = shared.tournaments()["cup"]
+                .rounds
+                .last()
+                .unwrap()
+                .iter()
+                .filter_map(|p| Some((p.gameid.clone()?, winner(&p.fleets))))
+                .collect();
+            for (gameid, winner) in games {
+                advance(shared, &gameid, &winner);
+            }
+        }
+    }
+
+    #[test]
+    fn round_robin_crowns_the_most_wins() {
+        let shared = tournament(TournamentFormat::RoundRobin, &["a", "b", "c"]);
+        // The fleet latest in the alphabet wins every game it plays
+        play_round_robin(&shared, |fleets| fleets.iter().max().unwrap().clone());
+        let tournaments = shared.tournaments();
+        let t = &tournaments["cup"];
+        assert_eq!(t.rounds.len(), 3);
+        assert_eq!(
+            t.wins(),
+            [
+                ("a".to_string(), 0),
+                ("b".to_string(), 1),
+                ("c".to_string(), 2)
+            ]
+            .into()
+        );
+        assert_eq!(t.champion.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn round_robin_ties_go_to_the_earliest_registered() {
+        let shared = tournament(TournamentFormat::RoundRobin, &["b", "c", "a"]);
+        // a beats b, b beats c and c beats a: every fleet wins one game
+        let beats = |x: &str, y: &str| matches!((x, y), ("a", "b") | ("b", "c") | ("c", "a"));
+        play_round_robin(&shared, |fleets| match beats(&fleets[0], &fleets[1]) {
+            true => fleets[0].clone(),
+            false => fleets[1].clone(),
+        });
+        let tournaments = shared.tournaments();
+        let t = &tournaments["cup"];
+        assert!(t.wins().values().all(|&wins| wins == 1));
+        assert_eq!(t.champion.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn rates_the_accounts_of_the_fleets() {
+        let players = HashMap::from([
+            ("a".to_string(), player(Some("alice"))),
+            ("b".to_string(), player(Some("alice"))),
+            ("c".to_string(), player(Some("carol"))),
+        ]);
+        let result = GameResult {
+            gameid: "g".to_string(),
+            winners: fleets(&["a", "b"]),
+            losers: fleets(&["c"]),
+        };
+        let rated = rated_result(&result, &players).unwrap();
+        assert_eq!(rated.winners, fleets(&["alice"]));
+        assert_eq!(rated.losers, fleets(&["carol"]));
+    }
+
+    #[test]
+    fn leaves_games_without_proven_opponents_unrated() {
+        let result = GameResult {
+            gameid: "g".to_string(),
+            winners: fleets(&["a"]),
+            losers: fleets(&["b"]),
+        };
+        let anonymous = HashMap::from([
+            ("a".to_string(), player(Some("alice"))),
+            ("b".to_string(), player(None)),
+        ]);
+        assert_eq!(rated_result(&result, &anonymous), None);
+        let same_account = HashMap::from([
+            ("a".to_string(), player(Some("alice"))),
+            ("b".to_string(), player(Some("alice"))),
+        ]);
+        assert_eq!(rated_result(&result, &same_account), None);
+    }
+}
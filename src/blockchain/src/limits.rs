@@ -0,0 +1,203 @@
+// src/limits.rs
+// Request rate limits of the emulator, so that a single client cannot saturate it with
+// receipts to verify.
+use axum::{
+    extract::{ConnectInfo, Extension, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header::RETRY_AFTER, StatusCode};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Clients tracked before idle ones are forgotten
+const MAX_KEYS: usize = 10_000;
+
+// Token bucket per key: up to `per_minute` requests at once, refilled continuously
+pub struct RateLimiter {
+    per_minute: u32,
+    max_keys: usize,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    // A limit of 0 accepts every request
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            max_keys: MAX_KEYS,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Take a token for `key`, or tell how long to wait for the next one
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(self.per_minute);
+        let per_second = capacity / 60.0;
+        let refilled = |bucket: &Bucket| {
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second)
+                .min(capacity)
+        };
+
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if buckets.len() >= self.max_keys && !buckets.contains_key(key) {
+            // Clients back to a full bucket are indistinguishable from new ones
+            buckets.retain(|_, bucket| refilled(bucket) < capacity);
+            // Past that, the clients seen least recently make room, so the map stays bounded
+            while buckets.len() >= self.max_keys {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(key, _)| key.clone());
+                match oldest {
+                    Some(oldest) => buckets.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+// Limits shared by the handlers
+pub struct Limits {
+    // Requests to the expensive routes from one address
+    pub ip: RateLimiter,
+    // Transactions from one fleet of a game
+    pub fleet: RateLimiter,
+}
+
+// Answer for a client over its limit, telling it when to come back
+pub fn too_many_requests(wait: Duration) -> Response {
+    let secs = wait.as_secs() + 1;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, secs.to_string())],
+        format!("Too many requests, retry in {} seconds", secs),
+    )
+        .into_response()
+}
+
+// Middleware applying the per address limit
+pub async fn limit_ip(
+    Extension(limits): Extension<Arc<Limits>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limits.ip.check(&addr.ip().to_string()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => too_many_requests(wait),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_minute: u32, max_keys: usize) -> RateLimiter {
+        RateLimiter {
+            max_keys,
+            ..RateLimiter::new(per_minute)
+        }
+    }
+
+    #[test]
+    fn burst_up_to_the_limit() {
+        let limiter = limiter(3, MAX_KEYS);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", now).is_ok());
+        }
+        let wait = limiter.check_at("a", now).unwrap_err();
+        assert_eq!(wait.as_secs(), 20);
+        // Other keys have their own bucket
+        assert!(limiter.check_at("b", now).is_ok());
+    }
+
+    #[test]
+    fn refill_over_time() {
+        let limiter = limiter(60, MAX_KEYS);
+        let now = Instant::now();
+        for _ in 0..60 {
+            assert!(limiter.check_at("a", now).is_ok());
+        }
+        assert!(limiter.check_at("a", now).is_err());
+        assert!(limiter.check_at("a", now + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at("a", now + Duration::from_secs(1)).is_err());
+        // A bucket never holds more than the limit
+        let later = now + Duration::from_secs(3600);
+        for _ in 0..60 {
+            assert!(limiter.check_at("a", later).is_ok());
+        }
+        assert!(limiter.check_at("a", later).is_err());
+    }
+
+    #[test]
+    fn no_limit() {
+        let limiter = limiter(0, 1);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert!(limiter.check_at("a", now).is_ok());
+        }
+    }
+
+    #[test]
+    fn eviction_keeps_a_hard_cap() {
+        let limiter = limiter(10, 3);
+        let now = Instant::now();
+        // Every client keeps a bucket below full, so none can be dropped as new
+        for (idx, key) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+            let at = now + Duration::from_millis(idx as u64);
+            assert!(limiter.check_at(key, at).is_ok());
+            assert!(limiter.buckets.lock().unwrap().len() <= 3);
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("a"));
+        assert!(!buckets.contains_key("b"));
+        assert!(buckets.contains_key("e"));
+    }
+
+    #[test]
+    fn eviction_prefers_full_buckets() {
+        let limiter = limiter(60, 2);
+        let now = Instant::now();
+        assert!(limiter.check_at("a", now).is_ok());
+        let later = now + Duration::from_secs(2);
+        assert!(limiter.check_at("b", later).is_ok());
+        // "a" refilled by now and goes first, although "b" is the older one below full
+        assert!(limiter.check_at("c", later).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("a"));
+        assert!(buckets.contains_key("b"));
+        assert!(buckets.contains_key("c"));
+    }
+}
@@ -3,71 +3,118 @@
 #![allow(unused_variables)]
 #![allow(dead_code)]
 
+mod config;
+mod limits;
+
 use axum::{
-    extract::Extension,
-    response::{sse::Event, Html, IntoResponse},
+    extract::{DefaultBodyLimit, Extension, Path},
+    middleware,
+    response::{sse::Event, Html, IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
+use clap::Parser;
 use futures::stream::StreamExt;
-use rand::{seq::IteratorRandom, SeedableRng};
-use risc0_zkvm::Digest;
-use std::{
-    collections::HashMap,
-    error::Error,
-    net::SocketAddr,
-    sync::{Arc, Mutex},
-};
+use http::StatusCode;
+use std::{error::Error, net::SocketAddr, sync::Arc};
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::BroadcastStream;
+use tower_http::timeout::TimeoutLayer;
 
-use fleetcore::{BaseJournal, Command, CommunicationData, FireJournal, ReportJournal};
-use methods::{FIRE_ID, JOIN_ID, REPORT_ID, WAVE_ID, WIN_ID};
+use config::ChainOptions;
+use limits::{Limits, RateLimiter};
 
-struct Player {
-    name: String,
-    current_state: Digest,
-}
-struct Game {
-    pmap: HashMap<String, Player>,
-    next_player: Option<String>,
-    next_report: Option<String>,
-    last_shot_pos: Option<u8>,
-    last_player: Option<String>,
-}
+use blockchain::SharedData;
+use fleetcore::{
+    AccountStatus, CommunicationData, FaucetRequest, ForfeitRequest, GameArchive, GameStatus,
+    GameSummary, LeaderboardEntry, MethodsInfo, RegisterRequest, ReplayStep, RevealData,
+    TournamentRequest, TournamentStatus,
+};
 
-#[derive(Clone)]
-struct SharedData {
-    tx: broadcast::Sender<String>,
-    gmap: Arc<Mutex<HashMap<String, Game>>>,
-    rng: Arc<Mutex<rand::rngs::StdRng>>,
+// Blockchain emulator for the battleship game
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    options: ChainOptions,
 }
 
 #[tokio::main]
 async fn main() {
-    // Create a broadcast channel for log messages
-    let (tx, _rx) = broadcast::channel::<String>(100);
-    let shared = SharedData {
-        tx: tx,
-        gmap: Arc::new(Mutex::new(HashMap::new())),
-        rng: Arc::new(Mutex::new(rand::rngs::StdRng::from_entropy())),
+    let config = match Cli::parse().options.resolve() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
     };
 
+    // Create a broadcast channel for log messages
+    let (tx, _rx) = broadcast::channel::<String>(config.log_capacity);
+    let shared = SharedData::new(tx, config.methods)
+        .with_scan_limit(config.scan_limit)
+        .with_wagers(config.wagers);
+    let limits = Arc::new(Limits {
+        ip: RateLimiter::new(config.ip_rate),
+        fleet: RateLimiter::new(config.fleet_rate),
+    });
+
     // Build our application with a route
 
+    // Routes verifying receipts, minting tokens or creating games are limited per client address
+    let verifying = Router::new()
+        .route("/chain", post(smart_contract))
+        .route("/reveal", post(reveal))
+        .route("/games/:gameid/replay", get(replay))
+        .route("/games/:gameid/summary", post(publish_summary))
+        .route("/games/:gameid/refund", post(refund))
+        .route("/faucet", post(faucet))
+        .route("/tournaments", post(create_tournament))
+        .route("/tournaments/:tournament/register", post(register_fleet))
+        .route("/tournaments/:tournament/start", post(start_tournament))
+        .route("/tournaments/:tournament/forfeit", post(forfeit))
+        .route_layer(middleware::from_fn(limits::limit_ip));
+
     let app = Router::new()
         .route("/", get(index))
         .route("/logs", get(logs))
-        .route("/chain", post(smart_contract))
-        .layer(Extension(shared));
+        .route("/methods", get(methods))
+        .route("/leaderboard", get(leaderboard))
+        .route("/accounts/:account", get(account))
+        .route("/tournaments", get(tournaments))
+        .route("/tournaments/:tournament", get(tournament_status))
+        .route("/games/:gameid", get(game_status))
+        .route("/games/:gameid/export", get(export))
+        .route("/games/:gameid/summary", get(summary))
+        .route("/spectate/:gameid", get(spectate))
+        .merge(verifying)
+        .layer(DefaultBodyLimit::max(config.max_body))
+        .layer(Extension(shared))
+        .layer(Extension(limits))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            config.request_timeout,
+        ));
 
     // Run our app with hyper
-    //let addr = SocketAddr::from(([127, 0, 0, 1], 3001));
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3001));
+    let addr = config.bind;
     println!("Listening on http://{}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to listen on {}: {}", addr, err);
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    {
+        eprintln!("Server error: {}", err);
+        std::process::exit(1);
+    }
 }
 
 // Handler to serve the HTML page
@@ -80,15 +127,97 @@ async fn index() -> Html<&'static str> {
             <title>Blockchain Emulator</title>
         </head>
         <body>
+            <form onsubmit="location.href = '/spectate/' + encodeURIComponent(this.game.value); return false;">
+                <input type="text" name="game" placeholder="Game ID">
+                <button type="submit">Spectate</button>
+            </form>
+            <h1>Leaderboard</h1>
+            <p>Elo ratings over the games whose summary proof was published</p>
+            <table>
+                <thead>
+                    <tr><th>Account</th><th>Rating</th><th>Games</th><th>Wins</th></tr>
+                </thead>
+                <tbody id="leaderboard"></tbody>
+            </table>
+            <h1>Tournaments</h1>
+            <div id="tournaments"></div>
             <h1>Registered Transactions</h1>          
             <ul id="logs"></ul>
             <script>
+                function refreshLeaderboard() {
+                    fetch('/leaderboard')
+                        .then(response => response.json())
+                        .then(entries => {
+                            const body = document.getElementById('leaderboard');
+                            body.innerHTML = '';
+                            for (const entry of entries) {
+                                const row = document.createElement('tr');
+                                for (const value of [entry.account, entry.rating, entry.games, entry.wins]) {
+                                    const cell = document.createElement('td');
+                                    cell.textContent = value;
+                                    row.appendChild(cell);
+                                }
+                                body.appendChild(row);
+                            }
+                        });
+                }
+                refreshLeaderboard();
+
+                function refreshTournaments() {
+                    fetch('/tournaments')
+                        .then(response => response.json())
+                        .then(tournaments => {
+                            const list = document.getElementById('tournaments');
+                            list.innerHTML = '';
+                            for (const t of tournaments) {
+                                const title = document.createElement('h2');
+                                const state = t.champion ? 'won by ' + t.champion
+                                    : t.rounds.length ? 'round ' + t.rounds.length
+                                    : 'registration open';
+                                title.textContent = t.tournament + ' (' + t.format + ', ' + state + ')';
+                                list.appendChild(title);
+                                const fleets = document.createElement('p');
+                                fleets.textContent = 'Fleets: ' + t.fleets
+                                    .map(fleet => fleet + ' ' + t.wins[fleet] + ' wins').join(', ');
+                                list.appendChild(fleets);
+                                t.rounds.forEach((round, idx) => {
+                                    const games = document.createElement('ul');
+                                    for (const pairing of round) {
+                                        const item = document.createElement('li');
+                                        item.append('Round ' + (idx + 1) + ': ' + pairing.fleets.join(' vs ') + ' ');
+                                        if (pairing.gameid) {
+                                            const link = document.createElement('a');
+                                            link.href = '/spectate/' + encodeURIComponent(pairing.gameid);
+                                            link.textContent = pairing.gameid;
+                                            item.appendChild(link);
+                                            if (pairing.winner) {
+                                                item.append(' won by ' + pairing.winner);
+                                            }
+                                        } else {
+                                            item.append('sits out');
+                                        }
+                                        games.appendChild(item);
+                                    }
+                                    list.appendChild(games);
+                                });
+                            }
+                        });
+                }
+                refreshTournaments();
+
                 const eventSource = new EventSource('/logs');
                 eventSource.onmessage = function(event) {
                     const logs = document.getElementById('logs');
                     const log = document.createElement('li');
                     log.textContent = event.data;
                     logs.appendChild(log);
+                    if (event.data.startsWith('Game ') && event.data.includes(' rated: ')) {
+                        refreshLeaderboard();
+                        refreshTournaments();
+                    }
+                    if (event.data.startsWith('Tournament ') || event.data.includes(' registered in tournament ')) {
+                        refreshTournaments();
+                    }
                 };
             </script>
         </body>
@@ -100,430 +229,190 @@ async fn index() -> Html<&'static str> {
 // Handler to manage SSE connections
 #[axum::debug_handler]
 async fn logs(Extension(shared): Extension<SharedData>) -> impl IntoResponse {
-    let rx = BroadcastStream::new(shared.tx.subscribe());
+    let rx = BroadcastStream::new(shared.subscribe());
+    // A subscriber too slow for the log capacity misses messages but stays subscribed
     let stream = rx.filter_map(|result| async move {
         match result {
-            Ok(msg) => Some(Ok(Event::default().data(msg))),
-            Err(_) => Some(Err(Box::<dyn Error + Send + Sync>::from("Error"))),
+            Ok(msg) => Some(Ok::<_, Box<dyn Error + Send + Sync>>(
+                Event::default().data(msg),
+            )),
+            Err(_) => None,
         }
     });
 
     axum::response::sse::Sse::new(stream)
 }
 
-fn xy_pos(pos: u8) -> String {
-    let x = pos % 10;
-    let y = pos / 10;
-    format!("{}{}", (x + 65) as char, y)
+// Handler to report the public state of a game
+async fn game_status(
+    Extension(shared): Extension<SharedData>,
+    Path(gameid): Path<String>,
+) -> Result<Json<GameStatus>, StatusCode> {
+    blockchain::game_status(&shared, &gameid)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-async fn smart_contract(
+// Handler bundling a game into an archive that can be verified offline
+async fn export(
     Extension(shared): Extension<SharedData>,
-    Json(input_data): Json<CommunicationData>,
-) -> String {
-    match input_data.cmd {
-        Command::Join => handle_join(&shared, &input_data),
-        Command::Fire => handle_fire(&shared, &input_data),
-        Command::Report => handle_report(&shared, &input_data),
-        Command::Wave => handle_wave(&shared, &input_data),
-        Command::Win => handle_win(&shared, &input_data),
-    }
+    Path(gameid): Path<String>,
+) -> Result<Json<GameArchive>, StatusCode> {
+    blockchain::export(&shared, &gameid)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-fn handle_join(shared: &SharedData, input_data: &CommunicationData) -> String {
-    if input_data.receipt.verify(JOIN_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to join game with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
-    }
-    let data: BaseJournal = input_data.receipt.journal.decode().unwrap();
-    let mut gmap = shared.gmap.lock().unwrap();
-    let game = gmap.entry(data.gameid.clone()).or_insert(Game {
-        pmap: HashMap::new(),
-        next_player: Some(data.fleet.clone()),
-        next_report: None,
-        last_shot_pos: None,
-        last_player: None,
-    });
-    let player_inserted = game
-        .pmap
-        .entry(data.fleet.clone())
-        .or_insert_with(|| Player {
-            name: data.fleet.clone(),
-            current_state: data.board.clone(),
-        })
-        .name
-        == data.fleet;
-    let mesg = if player_inserted {
-        format!("Player {} joined game {}", data.fleet, data.gameid)
-    } else {
-        format!("Player already in game {}", data.gameid)
-    };
-    shared.tx.send(mesg).unwrap();
-    "OK".to_string()
+// Handler re-verifying every accepted receipt of a game, in order, for replays
+async fn replay(
+    Extension(shared): Extension<SharedData>,
+    Path(gameid): Path<String>,
+) -> Result<Json<Vec<ReplayStep>>, StatusCode> {
+    // Verification is CPU bound, keep it off the async workers
+    tokio::task::spawn_blocking(move || blockchain::replay(&shared, &gameid))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-fn handle_fire(shared: &SharedData, input_data: &CommunicationData) -> String {
-    // Verify the receipt
-    if input_data.receipt.verify(FIRE_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to fire with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
-    }
-
-    // Decode the FireJournal from the receipt
-    let data: FireJournal = input_data.receipt.journal.decode().unwrap();
-
-    // Lock the game map to update the state
-    let mut gmap = shared.gmap.lock().unwrap();
-
-    // Find the game by game ID
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(game) => game,
-        None => {
-            let msg = format!("Game {} not found", data.gameid);
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-
-    // Find the firing player by fleet ID
-    let player = match game.pmap.get_mut(&data.fleet) {
-        Some(player) => player,
-        None => {
-            let msg = format!(
-                "Firing fleet {} not found in game {}",
-                data.fleet, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-
-    // Enforce turn order
-    match &game.next_player {
-        Some(expected_player) => {
-            if expected_player != &data.fleet {
-                let msg = format!(
-                    "It's not {}'s turn to fire in game {}. It's {}'s turn.",
-                    data.fleet, data.gameid, expected_player
-                );
-                shared.tx.send(msg.clone()).unwrap();
-                return msg;
-            }
-        }
-        None => {
-            let msg = format!(
-                "No player is allowed to fire right now in game {}. Awaiting report.",
-                data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    }
-
-    // Ensure the current state matches the board hash in the fire request
-    if player.current_state != data.board {
-        let msg = format!(
-            "Invalid fire: board hash does not match stored state for fleet {} in game {}",
-            data.fleet, data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
+// Handler publishing a single proof of a whole game, checked against its transactions
+async fn publish_summary(
+    Extension(shared): Extension<SharedData>,
+    Path(gameid): Path<String>,
+    Json(summary): Json<GameSummary>,
+) -> Result<String, StatusCode> {
+    if summary.gameid != gameid {
+        return Ok(format!("The summary is of game {}", summary.gameid));
     }
-
-    // Find the target player by fleet ID
-    let target_player = match game.pmap.get_mut(&data.target) {
-        Some(player) => player,
-        None => {
-            let msg = format!(
-                "Target fleet {} not found in game {}",
-                data.target, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-
-    // Set up for the next report
-    game.next_report = Some(data.target.clone());
-    game.next_player = None;
-    game.last_player = Some(data.fleet.clone());
-
-    // Broadcast the result of the fire action
-    let pos = xy_pos(data.pos);
-    let msg = format!(
-        "Player {} fired at {}'s fleet at position {} in game {}",
-        data.fleet, data.target, pos, data.gameid
-    );
-    shared.tx.send(msg.clone()).unwrap();
-
-    game.last_shot_pos = Some(data.pos); //Store the last shot position
-                                         // Return success
-    "OK".to_string()
+    // Verification is CPU bound, keep it off the async workers
+    tokio::task::spawn_blocking(move || blockchain::publish_summary(&shared, summary))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-fn handle_report(shared: &SharedData, input_data: &CommunicationData) -> String {
-    // Verify the receipt
-    if input_data.receipt.verify(REPORT_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to report with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
-    }
-
-    // Decode the ReportJournal from the receipt
-    let data: ReportJournal = input_data.receipt.journal.decode().unwrap();
-
-    // Lock the game map to update the state
-    let mut gmap = shared.gmap.lock().unwrap();
-
-    // Find the game by game ID
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(game) => game,
-        None => {
-            let msg = format!("Game {} not found", data.gameid);
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-
-    // Find the reporting player by fleet ID
-    let player = match game.pmap.get_mut(&data.fleet) {
-        Some(player) => player,
-        None => {
-            let msg = format!(
-                "Reporting fleet {} not found in game {}",
-                data.fleet, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-
-    // Enforce report order
-    if let Some(expected_reporter) = &game.next_report {
-        if expected_reporter != &data.fleet {
-            let msg = format!(
-                "It's not {}'s turn to report in game {}. It's {}'s turn.",
-                data.fleet, data.gameid, expected_reporter
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    } else {
-        let msg = format!("No report expected at this time in game {}.", data.gameid);
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
-    println!(
-        "DEBUG: Board currently in blockchain {}: {:?}",
-        data.fleet, data.board
-    );
-    println!(
-        "DEBUG: Player current state in blockchain {}: {:?}",
-        data.fleet, player.current_state
-    );
-
-    // Check if the last shot position is the same as the one being reported
-    if let Some(last_pos) = game.last_shot_pos {
-        if last_pos != data.pos {
-            let msg = format!(
-                "Invalid report: last shot position {} does not match reported position {} for fleet {} in game {}",
-                xy_pos(last_pos), xy_pos(data.pos), data.fleet, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    } else {
-        let msg = format!(
-            "Invalid report: no last shot position recorded for fleet {} in game {}",
-            data.fleet, data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
-
-    // CHECK: Ensure the current state matches the board hash in the report
-    // This check ensures that the player is reporting based on the correct board state.
-    if player.current_state != data.board {
-        let msg = format!(
-            "Invalid report: board hash does not match stored state for fleet {} in game {}",
-            data.fleet, data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
+// Handler serving the published summary of a game
+async fn summary(
+    Extension(shared): Extension<SharedData>,
+    Path(gameid): Path<String>,
+) -> Result<Json<GameSummary>, StatusCode> {
+    blockchain::summary(&shared, &gameid)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
 
-    // Update the player's state with the new board hash (next_board)
-    player.current_state = data.next_board.clone();
+// Handler listing the fleets of the rated games by rating
+async fn leaderboard(Extension(shared): Extension<SharedData>) -> Json<Vec<LeaderboardEntry>> {
+    Json(blockchain::leaderboard(&shared))
+}
 
-    // After a valid report, set the next player to the reporter
-    game.next_player = Some(data.fleet.clone());
-    game.next_report = None;
+// Handler reporting the tokens of an account
+async fn account(
+    Extension(shared): Extension<SharedData>,
+    Path(account): Path<String>,
+) -> Json<AccountStatus> {
+    Json(blockchain::account_status(&shared, &account))
+}
 
-    // Broadcast the result of the report action
-    let pos = xy_pos(data.pos);
-    let msg = format!(
-        "Player {} reported result '{}' at position {} in game {}",
-        data.fleet, data.report, pos, data.gameid
-    );
-    shared.tx.send(msg.clone()).unwrap();
+// Handler funding an account from the faucet
+async fn faucet(
+    Extension(shared): Extension<SharedData>,
+    Json(request): Json<FaucetRequest>,
+) -> String {
+    blockchain::faucet(&shared, &request.account)
+}
 
-    // Return success
-    "OK".to_string()
+// Handler giving back the stakes of a game nobody won in time
+async fn refund(Extension(shared): Extension<SharedData>, Path(gameid): Path<String>) -> String {
+    blockchain::refund(&shared, &gameid)
 }
 
-fn handle_wave(shared: &SharedData, input_data: &CommunicationData) -> String {
-    // Verify the receipt
-    if input_data.receipt.verify(WAVE_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to wave with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
-    }
+// Handler opening a tournament for registration
+async fn create_tournament(
+    Extension(shared): Extension<SharedData>,
+    Json(request): Json<TournamentRequest>,
+) -> String {
+    blockchain::create_tournament(&shared, request)
+}
 
-    // Decode the BaseJournal from the receipt
-    let data: BaseJournal = input_data.receipt.journal.decode().unwrap();
+// Handler registering a fleet in a tournament
+async fn register_fleet(
+    Extension(shared): Extension<SharedData>,
+    Path(tournament): Path<String>,
+    Json(request): Json<RegisterRequest>,
+) -> String {
+    blockchain::register_fleet(&shared, &tournament, &request.fleet)
+}
 
-    // Lock the game map to update the state
-    let mut gmap = shared.gmap.lock().unwrap();
+// Handler closing the registration of a tournament and pairing its first round
+async fn start_tournament(
+    Extension(shared): Extension<SharedData>,
+    Path(tournament): Path<String>,
+) -> String {
+    blockchain::start_tournament(&shared, &tournament)
+}
 
-    // Find the game by game ID
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(game) => game,
-        None => {
-            let msg = format!("Game {} not found", data.gameid);
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-    // Find the reporting player by fleet ID
-    let player = match game.pmap.get_mut(&data.fleet) {
-        Some(player) => player,
-        None => {
-            let msg = format!(
-                "Reporting fleet {} not found in game {}",
-                data.fleet, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
+// Handler deciding a stalled game of a tournament by forfeit
+async fn forfeit(
+    Extension(shared): Extension<SharedData>,
+    Path(tournament): Path<String>,
+    Json(request): Json<ForfeitRequest>,
+) -> String {
+    blockchain::forfeit(&shared, &tournament, &request.gameid)
+}
 
-    // This check ensures that the player is reporting based on the correct board state.
-    if player.current_state != data.board {
-        let msg = format!(
-            "Invalid report: board hash does not match stored state for fleet {} in game {}",
-            data.fleet, data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
-    // Enforce turn order
-    if let Some(expected_player) = &game.next_player {
-        if expected_player != &data.fleet {
-            let msg = format!(
-                "It's not {}'s turn to wave in game {}. It's {}'s turn.",
-                data.fleet, data.gameid, expected_player
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    } else {
-        let msg = format!(
-            "No player is allowed to wave right now in game {}. Awaiting report.",
-            data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
+// Handler listing the tournaments with their rounds
+async fn tournaments(Extension(shared): Extension<SharedData>) -> Json<Vec<TournamentStatus>> {
+    Json(blockchain::tournaments(&shared))
+}
 
-    // Broadcast the wave action
-    let msg = format!(
-        "Player {} waved their turn on game {}",
-        data.fleet, data.gameid
-    );
-    shared.tx.send(msg.clone()).unwrap();
-
-    // If the player waves their turn, we need to set the next player which is the last player
-    if let Some(last_player) = &game.last_player {
-        game.next_player = Some(last_player.clone());
-    } else {
-        // If no last player, we can't set the next player
-        let msg = format!(
-            "No last player found to set next player in game {}",
-            data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
+// Handler reporting the state of a tournament
+async fn tournament_status(
+    Extension(shared): Extension<SharedData>,
+    Path(tournament): Path<String>,
+) -> Result<Json<TournamentStatus>, StatusCode> {
+    blockchain::tournament_status(&shared, &tournament)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
 
-    // Set last player to current player
-    game.last_player = Some(data.fleet.clone());
-    // Return success
-    "OK".to_string()
+// Handler listing the guest versions accepted, for hosts to check compatibility
+async fn methods(Extension(shared): Extension<SharedData>) -> Json<MethodsInfo> {
+    Json(shared.methods().info())
 }
 
-fn handle_win(shared: &SharedData, input_data: &CommunicationData) -> String {
-    // Verify the receipt
-    if input_data.receipt.verify(WIN_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to claim win with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
-    }
+// Handler to serve the spectator page of a game; the script reads the game ID from the path
+async fn spectate() -> Html<&'static str> {
+    Html(SPECTATE_PAGE)
+}
 
-    // Decode the BaseJournal from the receipt
-    let data: BaseJournal = input_data.receipt.journal.decode().unwrap();
-    // Lock the game map to update the state
-    let mut gmap = shared.gmap.lock().unwrap();
-
-    // Find the game by game ID
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(game) => game,
-        None => {
-            let msg = format!("Game {} not found", data.gameid);
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
-        }
-    };
-    // Find the reporting player by fleet ID
-    let player = match game.pmap.get_mut(&data.fleet) {
-        Some(player) => player,
-        None => {
-            let msg = format!(
-                "Reporting fleet {} not found in game {}",
-                data.fleet, data.gameid
-            );
-            shared.tx.send(msg.clone()).unwrap();
-            return msg;
+async fn smart_contract(
+    Extension(shared): Extension<SharedData>,
+    Extension(limits): Extension<Arc<Limits>>,
+    Json(input_data): Json<CommunicationData>,
+) -> Response {
+    // Verification is CPU bound, keep it off the async workers
+    tokio::task::spawn_blocking(move || {
+        let verified = match blockchain::verify(&shared, &input_data) {
+            Ok(verified) => verified,
+            Err(err) => return err.into_response(),
+        };
+        // Only a verified receipt is charged to its fleet, so that forged receipts naming a
+        // fleet cannot lock it out; unverified traffic is limited per address only
+        let key = format!("{}/{}", verified.gameid(), verified.fleet());
+        if let Err(wait) = limits.fleet.check(&key) {
+            return limits::too_many_requests(wait);
         }
-    };
-
-    // This check ensures that the player is reporting based on the correct board state.
-    if player.current_state != data.board {
-        let msg = format!(
-            "Invalid report: board hash does not match stored state for fleet {} in game {}",
-            data.fleet, data.gameid
-        );
-        shared.tx.send(msg.clone()).unwrap();
-        return msg;
-    }
-
-    // Broadcast the win claim
-    let msg = format!(
-        "Player {} claims victory in game {}!",
-        data.fleet, data.gameid
-    );
-    shared.tx.send(msg.clone()).unwrap();
+        blockchain::apply(&shared, &input_data, &verified).into_response()
+    })
+    .await
+    .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
 
-    "OK".to_string()
+// Handler for a fleet publishing the board and salt it joined with
+async fn reveal(Extension(shared): Extension<SharedData>, Json(data): Json<RevealData>) -> String {
+    blockchain::reveal(&shared, data)
 }
+
+// Spectator page listing the boards of a game, live or replayed from its receipts
+const SPECTATE_PAGE: &str = include_str!("spectate.html");
@@ -0,0 +1,142 @@
+// src/ratings.rs
+// Elo ratings of ledger accounts, replayed from the rated games in the order they were
+// recorded. A player is rated as the account its join receipts prove the key of, whatever
+// fleet ID it plays under; opening a new account starts a new rating.
+use fleetcore::{GameResult, LeaderboardEntry};
+use std::collections::HashMap;
+
+// Rating of an account before its first rated game
+const INITIAL_RATING: f64 = 1500.0;
+// Most a rating moves in a single game
+const K_FACTOR: f64 = 32.0;
+
+// Rating, games and wins of an account
+type Standing = (f64, u32, u32);
+
+// Accounts of the rated games, best rating first
+pub fn leaderboard(results: &[GameResult]) -> Vec<LeaderboardEntry> {
+    let mut standings: HashMap<&str, Standing> = HashMap::new();
+    for result in results {
+        if result.winners.is_empty() || result.losers.is_empty() {
+            continue;
+        }
+        let rating = |account: &str| standings.get(account).map_or(INITIAL_RATING, |s| s.0);
+        // A game against several opponents counts as one game against each, averaged, all
+        // scored on the ratings before the game
+        let mut changes: Vec<(&str, f64, bool)> = Vec::new();
+        for winner in &result.winners {
+            let gain: f64 = result
+                .losers
+                .iter()
+                .map(|loser| 1.0 - expected(rating(winner), rating(loser)))
+                .sum();
+            changes.push((winner, K_FACTOR * gain / result.losers.len() as f64, true));
+        }
+        for loser in &result.losers {
+            let loss: f64 = result
+                .winners
+                .iter()
+                .map(|winner| expected(rating(loser), rating(winner)))
+                .sum();
+            changes.push((loser, -K_FACTOR * loss / result.winners.len() as f64, false));
+        }
+        for (account, change, won) in changes {
+            let standing = standings.entry(account).or_insert((INITIAL_RATING, 0, 0));
+            standing.0 += change;
+            standing.1 += 1;
+            if won {
+                standing.2 += 1;
+            }
+        }
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = standings
+        .into_iter()
+        .map(|(account, (rating, games, wins))| LeaderboardEntry {
+            account: account.to_string(),
+            rating: rating.round() as i32,
+            games,
+            wins,
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.rating
+            .cmp(&a.rating)
+            .then_with(|| a.account.cmp(&b.account))
+    });
+    entries
+}
+
+// Probability for an account rated `rating` to beat one rated `opponent`
+fn expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(gameid: &str, winners: &[&str], losers: &[&str]) -> GameResult {
+        GameResult {
+            gameid: gameid.to_string(),
+            winners: winners.iter().map(|f| f.to_string()).collect(),
+            losers: losers.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    fn standing(entries: &[LeaderboardEntry], account: &str) -> (i32, u32, u32) {
+        let entry = entries.iter().find(|e| e.account == account).unwrap();
+        (entry.rating, entry.games, entry.wins)
+    }
+
+    #[test]
+    fn even_game_moves_half_the_k_factor() {
+        let entries = leaderboard(&[result("g1", &["a"], &["b"])]);
+        assert_eq!(standing(&entries, "a"), (1516, 1, 1));
+        assert_eq!(standing(&entries, "b"), (1484, 1, 0));
+    }
+
+    #[test]
+    fn favourite_gains_less_than_an_underdog() {
+        let entries = leaderboard(&[result("g1", &["a"], &["b"]), result("g2", &["a"], &["b"])]);
+        assert_eq!(standing(&entries, "a"), (1531, 2, 2));
+        assert_eq!(standing(&entries, "b"), (1469, 2, 0));
+
+        let entries = leaderboard(&[result("g1", &["a"], &["b"]), result("g2", &["b"], &["a"])]);
+        assert_eq!(standing(&entries, "b"), (1501, 2, 1));
+        assert_eq!(standing(&entries, "a"), (1499, 2, 1));
+    }
+
+    #[test]
+    fn teammates_share_the_result() {
+        let entries = leaderboard(&[result("g1", &["a", "b"], &["c", "d"])]);
+        for account in ["a", "b"] {
+            assert_eq!(standing(&entries, account), (1516, 1, 1));
+        }
+        for account in ["c", "d"] {
+            assert_eq!(standing(&entries, account), (1484, 1, 0));
+        }
+    }
+
+    #[test]
+    fn several_opponents_are_averaged() {
+        let entries = leaderboard(&[result("g1", &["a"], &["b", "c"])]);
+        assert_eq!(standing(&entries, "a"), (1516, 1, 1));
+        assert_eq!(standing(&entries, "b"), (1484, 1, 0));
+        assert_eq!(standing(&entries, "c"), (1484, 1, 0));
+    }
+
+    #[test]
+    fn games_without_a_loser_are_not_rated() {
+        // Games end with a winner only, so a result without losers, like a draw, rates nobody
+        let entries = leaderboard(&[result("g1", &["a", "b"], &[]), result("g2", &[], &["c"])]);
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn best_rating_first_ties_by_account() {
+        let entries = leaderboard(&[result("g1", &["d"], &["c"]), result("g2", &["b"], &["a"])]);
+        let accounts: Vec<&str> = entries.iter().map(|e| e.account.as_str()).collect();
+        assert_eq!(accounts, ["b", "d", "a", "c"]);
+    }
+}
@@ -0,0 +1,230 @@
+// src/registry.rs
+// Guest versions accepted by the chain. The guests built into this binary are the current
+// version; older releases are listed in the config file so that games started under them
+// can finish while hosts are upgraded.
+use risc0_zkvm::{sha::Digestible, Digest, Receipt};
+use serde::Deserialize;
+use std::path::Path;
+
+use fleetcore::{Command, MethodId, MethodVersion, MethodsInfo};
+use methods::{
+    FIRE_ID, JOIN_ID, METHODS_VERSION, MOVE_ID, REPORT_ID, RESIGN_ID, SALVO_ID, SALVO_REPORT_ID,
+    SCAN_ID, SCAN_REQUEST_ID, WAVE_ID, WIN_ID,
+};
+
+const COMMANDS: [Command; 11] = [
+    Command::Join,
+    Command::Fire,
+    Command::Report,
+    Command::Wave,
+    Command::Win,
+    Command::Salvo,
+    Command::SalvoReport,
+    Command::ScanRequest,
+    Command::Scan,
+    Command::Move,
+    Command::Resign,
+];
+
+// Image IDs of a guest release as written in the config file, in hex
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodIds {
+    pub version: String,
+    pub join: String,
+    pub fire: String,
+    pub report: String,
+    pub wave: String,
+    pub win: String,
+    // Releases before the salvo mode have no salvo guests
+    #[serde(default)]
+    pub salvo: Option<String>,
+    #[serde(default)]
+    pub salvo_report: Option<String>,
+    // Nor those before scans any scan guests
+    #[serde(default)]
+    pub scan_request: Option<String>,
+    #[serde(default)]
+    pub scan: Option<String>,
+    // Nor those before moving ships a move guest
+    #[serde(default, rename = "move")]
+    pub move_ship: Option<String>,
+    // Nor those before resigning a resign guest
+    #[serde(default)]
+    pub resign: Option<String>,
+}
+
+impl MethodIds {
+    fn parse(&self) -> Result<MethodVersion, String> {
+        let image_ids = COMMANDS
+            .into_iter()
+            .zip([
+                Some(&self.join),
+                Some(&self.fire),
+                Some(&self.report),
+                Some(&self.wave),
+                Some(&self.win),
+                self.salvo.as_ref(),
+                self.salvo_report.as_ref(),
+                self.scan_request.as_ref(),
+                self.scan.as_ref(),
+                self.move_ship.as_ref(),
+                self.resign.as_ref(),
+            ])
+            .filter_map(|(cmd, id)| id.map(|id| (cmd, id)))
+            .map(|(cmd, id)| {
+                let bytes = hex::decode(id).map_err(|err| {
+                    format!(
+                        "Invalid {:?} image ID of version {}: {}",
+                        cmd, self.version, err
+                    )
+                })?;
+                let id = Digest::try_from(bytes).map_err(|_| {
+                    format!(
+                        "Invalid {:?} image ID of version {}: expected 32 bytes",
+                        cmd, self.version
+                    )
+                })?;
+                Ok(MethodId { cmd, id })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(MethodVersion {
+            version: self.version.clone(),
+            image_ids,
+        })
+    }
+}
+
+// The [[methods]] tables of a chain config file, for tools that need nothing else from it
+#[derive(Default, Deserialize)]
+struct MethodsFile {
+    #[serde(default)]
+    methods: Vec<MethodIds>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MethodRegistry {
+    // The built-in version comes first
+    versions: Vec<MethodVersion>,
+}
+
+impl MethodRegistry {
+    // Registry accepting only the guests built into this binary
+    pub fn builtin() -> Self {
+        let ids = [
+            JOIN_ID,
+            FIRE_ID,
+            REPORT_ID,
+            WAVE_ID,
+            WIN_ID,
+            SALVO_ID,
+            SALVO_REPORT_ID,
+            SCAN_REQUEST_ID,
+            SCAN_ID,
+            MOVE_ID,
+            RESIGN_ID,
+        ];
+        MethodRegistry {
+            versions: vec![MethodVersion {
+                version: METHODS_VERSION.to_string(),
+                image_ids: COMMANDS
+                    .into_iter()
+                    .zip(ids)
+                    .map(|(cmd, id)| MethodId { cmd, id: id.into() })
+                    .collect(),
+            }],
+        }
+    }
+
+    // Built-in guests plus older releases still accepted
+    pub fn with_versions(extra: &[MethodIds]) -> Result<Self, String> {
+        let mut registry = Self::builtin();
+        for ids in extra {
+            let version = ids.parse()?;
+            if registry.version(&version.version).is_some() {
+                return Err(format!("Guest version {} listed twice", version.version));
+            }
+            registry.versions.push(version);
+        }
+        Ok(registry)
+    }
+
+    // Registry of the offline verifiers: built-in guests plus the versions listed in a chain
+    // config file, if given
+    pub fn from_config(path: Option<&Path>) -> Result<Self, String> {
+        let file = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+                toml::from_str::<MethodsFile>(&text)
+                    .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?
+            }
+            None => MethodsFile::default(),
+        };
+        Self::with_versions(&file.methods)
+    }
+
+    pub fn current(&self) -> &str {
+        &self.versions[0].version
+    }
+
+    pub fn version(&self, version: &str) -> Option<&MethodVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    pub fn info(&self) -> MethodsInfo {
+        MethodsInfo {
+            current: self.current().to_string(),
+            versions: self.versions.clone(),
+        }
+    }
+
+    // Version whose guests have exactly the given image IDs
+    pub fn version_of(&self, image_ids: &[MethodId]) -> Option<&str> {
+        self.versions
+            .iter()
+            .find(|v| {
+                v.image_ids.len() == image_ids.len()
+                    && v.image_ids.iter().all(|m| image_ids.contains(m))
+            })
+            .map(|v| v.version.as_str())
+    }
+
+    // Verify a receipt against the image ID of a command in the given version
+    pub fn verify(&self, version: &str, cmd: Command, receipt: &Receipt) -> Result<(), String> {
+        let id = self
+            .version(version)
+            .and_then(|v| v.image_ids.iter().find(|m| m.cmd == cmd))
+            .ok_or_else(|| format!("Guest version {} is not accepted", version))?;
+        receipt.verify(id.id).map_err(|err| err.to_string())
+    }
+
+    // First version, current one first, whose image ID of the command verifies the receipt
+    pub fn find_version(&self, cmd: Command, receipt: &Receipt) -> Option<&str> {
+        let version = self.claimed_version(cmd, receipt).ok()?;
+        self.verify(version, cmd, receipt).ok().map(|()| version)
+    }
+
+    // First version whose image ID of the command is the one the receipt claims. The claim
+    // is not verified, but reading it is cheap: receipts of unknown guests can be rejected
+    // before spending time on a full verification.
+    pub fn claimed_version(&self, cmd: Command, receipt: &Receipt) -> Result<&str, String> {
+        let id = claimed_image_id(receipt)?;
+        self.versions
+            .iter()
+            .find(|v| v.image_ids.iter().any(|m| m.cmd == cmd && m.id == id))
+            .map(|v| v.version.as_str())
+            .ok_or_else(|| format!("Unknown image ID {} for {:?}", id, cmd))
+    }
+}
+
+// Image ID of the guest a receipt claims to come from, read without verifying the receipt
+fn claimed_image_id(receipt: &Receipt) -> Result<Digest, String> {
+    let claim = receipt
+        .claim()
+        .map_err(|err| format!("Invalid receipt claim: {}", err))?;
+    let claim = claim
+        .as_value()
+        .map_err(|_| "Receipt claim is pruned".to_string())?;
+    Ok(claim.pre.digest())
+}
@@ -0,0 +1,223 @@
+// src/tournaments.rs
+// Tournaments run by the emulator. Registered fleets are paired round by round, every pairing
+// playing a game reserved to its fleets; a round is over once each of its games has a result
+// proven by a published summary.
+use fleetcore::{Pairing, TournamentFormat, TournamentStatus};
+use std::collections::BTreeMap;
+
+use crate::GameRules;
+
+pub struct Tournament {
+    pub format: TournamentFormat,
+    // Rules fixed when the tournament is opened, which every game of it is pinned to
+    pub rules: GameRules,
+    pub fleets: Vec<String>,
+    pub rounds: Vec<Vec<Pairing>>,
+    pub champion: Option<String>,
+}
+
+impl Tournament {
+    pub fn new(format: TournamentFormat, rules: GameRules) -> Self {
+        Tournament {
+            format,
+            rules,
+            fleets: Vec::new(),
+            rounds: Vec::new(),
+            champion: None,
+        }
+    }
+
+    pub fn started(&self) -> bool {
+        !self.rounds.is_empty()
+    }
+
+    pub fn status(&self, name: &str) -> TournamentStatus {
+        TournamentStatus {
+            tournament: name.to_string(),
+            format: self.format,
+            version: self.rules.version.clone(),
+            scan_limit: self.rules.scan_limit,
+            stake: self.rules.stake,
+            fleets: self.fleets.clone(),
+            rounds: self.rounds.clone(),
+            wins: self.wins(),
+            champion: self.champion.clone(),
+        }
+    }
+
+    // Games won by each registered fleet; sitting a round out wins nothing
+    pub fn wins(&self) -> BTreeMap<String, u32> {
+        let mut wins: BTreeMap<String, u32> = self.fleets.iter().map(|f| (f.clone(), 0)).collect();
+        for pairing in self.rounds.iter().flatten() {
+            if let (Some(_), Some(winner)) = (&pairing.gameid, &pairing.winner) {
+                *wins.entry(winner.clone()).or_insert(0) += 1;
+            }
+        }
+        wins
+    }
+
+    // Rounds each fleet sat out
+    fn byes(&self, fleet: &str) -> usize {
+        self.rounds
+            .iter()
+            .flatten()
+            .filter(|p| p.gameid.is_none() && p.fleets.iter().any(|f| f == fleet))
+            .count()
+    }
+
+    // Fleets paired in the next round, None once the tournament is over. In a bracket with an
+    // odd number of entrants, the latest of those who sat out the fewest rounds sits this
+    // one out, so no fleet sits out twice while another has never had to.
+    pub fn next_round(&self) -> Option<Vec<Vec<String>>> {
+        match self.format {
+            TournamentFormat::Bracket => {
+                let mut entrants: Vec<String> = match self.rounds.last() {
+                    Some(round) => round.iter().filter_map(|p| p.winner.clone()).collect(),
+                    None => self.fleets.clone(),
+                };
+                if entrants.len() < 2 {
+                    return None;
+                }
+                let bye = match entrants.len() % 2 {
+                    1 => (0..entrants.len())
+                        .rev()
+                        .min_by_key(|&idx| self.byes(&entrants[idx]))
+                        .map(|idx| entrants.remove(idx)),
+                    _ => None,
+                };
+                let mut pairings: Vec<Vec<String>> =
+                    entrants.chunks(2).map(<[String]>::to_vec).collect();
+                pairings.extend(bye.map(|fleet| vec![fleet]));
+                Some(pairings)
+            }
+            TournamentFormat::RoundRobin => {
+                round_robin(&self.fleets).into_iter().nth(self.rounds.len())
+            }
+        }
+    }
+
+    // Undecided pairing of the current round playing game `gameid`
+    pub fn pending(&self, gameid: &str) -> Option<&Pairing> {
+        self.rounds
+            .last()?
+            .iter()
+            .find(|p| p.gameid.as_deref() == Some(gameid) && p.winner.is_none())
+    }
+
+    // Record the winner of a game of the current round, one of the fleets paired in it,
+    // telling whether the round is over
+    pub fn record(&mut self, gameid: &str, winner: &str) -> bool {
+        let Some(round) = self.rounds.last_mut() else {
+            return false;
+        };
+        let Some(pairing) = round.iter_mut().find(|p| {
+            p.gameid.as_deref() == Some(gameid)
+                && p.winner.is_none()
+                && p.fleets.iter().any(|fleet| fleet == winner)
+        }) else {
+            return false;
+        };
+        pairing.winner = Some(winner.to_string());
+        round.iter().all(|p| p.winner.is_some())
+    }
+
+    // Champion of a tournament whose last round is over: the winner of the bracket final, or
+    // the fleet with the most wins of a round robin, ties going to the earliest registered
+    pub fn crown(&mut self) {
+        self.champion = match self.format {
+            TournamentFormat::Bracket => self
+                .rounds
+                .last()
+                .and_then(|round| round.first())
+                .and_then(|p| p.winner.clone()),
+            TournamentFormat::RoundRobin => {
+                let wins = self.wins();
+                self.fleets
+                    .iter()
+                    .rev()
+                    .max_by_key(|fleet| wins.get(*fleet))
+                    .cloned()
+            }
+        };
+    }
+}
+
+// Rounds of a round robin by the circle method, so that every fleet meets every other once.
+// With an odd number of fleets one of them sits each round out.
+pub fn round_robin(fleets: &[String]) -> Vec<Vec<Vec<String>>> {
+    let mut slots: Vec<Option<&String>> = fleets.iter().map(Some).collect();
+    if slots.len() % 2 == 1 {
+        slots.push(None);
+    }
+    let count = slots.len();
+    let mut rounds = Vec::new();
+    for _ in 1..count {
+        let round = (0..count / 2)
+            .map(|i| {
+                [slots[i], slots[count - 1 - i]]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        rounds.push(round);
+        // The first fleet stays put while the others rotate
+        slots[1..].rotate_right(1);
+    }
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn fleets(names: &[&str]) -> Vec<String> {
+        names.iter().map(|f| f.to_string()).collect()
+    }
+
+    // Every pair of fleets meeting in the rounds, checking that none meets twice
+    fn meetings(rounds: &[Vec<Vec<String>>]) -> BTreeSet<(String, String)> {
+        let mut met = BTreeSet::new();
+        for pairing in rounds.iter().flatten().filter(|p| p.len() == 2) {
+            let mut pair = [pairing[0].clone(), pairing[1].clone()];
+            pair.sort();
+            let [first, second] = pair;
+            assert!(met.insert((first, second)), "{:?} met twice", pairing);
+        }
+        met
+    }
+
+    #[test]
+    fn round_robin_pairs_an_even_field() {
+        let names = fleets(&["a", "b", "c", "d"]);
+        let rounds = round_robin(&names);
+        assert_eq!(rounds.len(), 3);
+        for round in &rounds {
+            let mut playing: Vec<&String> = round.iter().flatten().collect();
+            playing.sort();
+            assert_eq!(playing, names.iter().collect::<Vec<_>>());
+            assert!(round.iter().all(|p| p.len() == 2));
+        }
+        assert_eq!(meetings(&rounds).len(), 6);
+    }
+
+    #[test]
+    fn round_robin_sits_one_fleet_out_of_an_odd_field() {
+        let names = fleets(&["a", "b", "c", "d", "e"]);
+        let rounds = round_robin(&names);
+        assert_eq!(rounds.len(), 5);
+        let mut sat_out: Vec<&String> = rounds
+            .iter()
+            .map(|round| {
+                let byes: Vec<&Vec<String>> = round.iter().filter(|p| p.len() == 1).collect();
+                assert_eq!(byes.len(), 1);
+                &byes[0][0]
+            })
+            .collect();
+        sat_out.sort();
+        assert_eq!(sat_out, names.iter().collect::<Vec<_>>());
+        assert_eq!(meetings(&rounds).len(), 10);
+    }
+}
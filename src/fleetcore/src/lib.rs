@@ -1,7 +1,12 @@
 use risc0_zkvm::{Digest, Receipt};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::collections::{BTreeMap, HashSet};
 
-// Struct sent by the rust code for input on the methods join, wave and win
+// Number of cells occupied by a complete fleet
+pub const FLEET_CELLS: usize = 18;
+
+// Struct sent by the rust code for input on the methods wave and win
 // The struct is read by the zkvm code and the data is used to generate the output Journal
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct BaseInputs {
@@ -11,6 +16,19 @@ pub struct BaseInputs {
     pub random: String,
 }
 
+// Struct sent by the rust code for input on the join method. A fleet may declare a team,
+// whose fleets share their turns and cannot fire at each other, and stake from the account
+// whose secret key it holds.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct JoinInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub team: Option<String>,
+    pub account_key: Option<String>,
+}
+
 // Struct sent by the rust code for input on the methods fire and report
 // The struct is read by the zkvm code and the data is used to generate the output Journal
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -21,26 +39,75 @@ pub struct FireInputs {
     pub random: String,
     pub target: String,
     pub pos: u8,
+    // Shots the fleet fired before, in order; only read by the fire method
+    pub shots: Vec<Shot>,
+}
+
+// Shot fired by a fleet, as committed in its shot history
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Shot {
+    pub target: String,
+    pub pos: u8,
 }
 
 // Enum used to define the command that will be sent to the server by the host in the communication packet
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub enum Command {
     Join,
     Fire,
     Report,
     Wave,
     Win,
+    Salvo,
+    SalvoReport,
+    ScanRequest,
+    Scan,
+    Move,
+    Resign,
+}
+
+// Rules of a game, fixed by its first shot: one shot per turn, or in salvo mode one shot per
+// ship still afloat
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Salvo,
+}
+
+// Struct sent by the rust code for input on the salvo method. The board the fleet joined
+// with tells which of the remaining cells belong to the same ship.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SalvoInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub joined: Vec<u8>,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub target: String,
+    pub positions: Vec<u8>,
+    // Shots the fleet fired before, in order
+    pub shots: Vec<Shot>,
+}
+
+// Struct sent by the rust code for input on the salvo report method
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SalvoReportInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub positions: Vec<u8>,
 }
 
 // Struct used to specify the packet sent from the client to the blockchain server
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct CommunicationData {
     pub cmd: Command,
     pub receipt: Receipt,
 }
 
-// Struct to specify the  output journal for join, wave and win methods
+// Struct to specify the  output journal for wave and win methods
 #[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
 pub struct BaseJournal {
     pub gameid: String,
@@ -48,6 +115,38 @@ pub struct BaseJournal {
     pub board: Digest,
 }
 
+// Struct sent by the rust code for input on the resign method. With `reveal` set, the
+// journal also shows the cells of the fleet still afloat.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResignInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub reveal: bool,
+}
+
+// Struct to specify the output journal for resign method
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct ResignJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Digest,
+    // Cells the board commitment opens to, in committed order, if the fleet chose to show them
+    pub remaining: Option<Vec<u8>>,
+}
+
+// Struct to specify the output journal for join method
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct JoinJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Digest,
+    pub team: Option<String>,
+    // Ledger account the stake of the fleet is taken from
+    pub account: Option<String>,
+}
+
 // Struct to specify the  output journal for fire method
 #[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
 pub struct FireJournal {
@@ -56,6 +155,9 @@ pub struct FireJournal {
     pub board: Digest,
     pub target: String,
     pub pos: u8,
+    // Shot history of the fleet before and after this shot
+    pub shots: Digest,
+    pub next_shots: Digest,
 }
 
 // Struct to specify the  output journal for report method
@@ -68,3 +170,751 @@ pub struct ReportJournal {
     pub board: Digest,
     pub next_board: Digest,
 }
+
+// Struct sent by the rust code for input on the scan request method: the requesting fleet
+// opens its own commitment, as when firing, and names the area to scan
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ScanRequestInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub target: String,
+    pub center: u8,
+}
+
+// Struct sent by the rust code for input on the scan method, run by the scanned fleet
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ScanInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub center: u8,
+}
+
+// Direction of a ship move, by one cell
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// Move of the ship holding `cell`. Moves stay private until the fleet reveals its board.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ShipMove {
+    pub cell: u8,
+    pub direction: Direction,
+}
+
+// Struct sent by the rust code for input on the move method. The cells hit so far are
+// public; with the board they make up the whole fleet. No ship may move onto a cell
+// already shot at, which could never be shot again.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MoveInputs {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    pub hits: Vec<u8>,
+    pub misses: Vec<u8>,
+    pub ship_move: ShipMove,
+}
+
+// Struct to specify the output journal for move method: the move itself stays hidden
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct MoveJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Digest,
+    pub next_board: Digest,
+    // Cells hit, sorted, the fleet rules were checked with
+    pub hits: Vec<u8>,
+    // Cells shot and missed, sorted, the ship was kept off
+    pub misses: Vec<u8>,
+}
+
+// Struct to specify the output journal for scan request method
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct ScanRequestJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Digest,
+    pub target: String,
+    pub center: u8,
+}
+
+// Struct to specify the output journal for scan method: only the number of ship cells in the
+// area is made public
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct ScanJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Digest,
+    pub center: u8,
+    pub count: u8,
+}
+
+// Struct to specify the output journal for salvo method
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct SalvoJournal {
+    pub gameid: String,
+    pub fleet: String,
+    // Commitment made when joining, and the current one
+    pub initial: Digest,
+    pub board: Digest,
+    pub target: String,
+    pub positions: Vec<u8>,
+    // Shot history of the fleet before and after this salvo
+    pub shots: Digest,
+    pub next_shots: Digest,
+}
+
+// Struct to specify the output journal for salvo report method, with one report per position
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct SalvoReportJournal {
+    pub gameid: String,
+    pub fleet: String,
+    pub positions: Vec<u8>,
+    pub reports: Vec<String>,
+    pub board: Digest,
+    pub next_board: Digest,
+}
+
+// Struct returned by the blockchain server with the public state of a game
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct GameStatus {
+    pub gameid: String,
+    pub players: Vec<String>,
+    pub next_player: Option<String>,
+    pub next_report: Option<String>,
+    pub last_shot_pos: Option<u8>,
+    pub last_player: Option<String>,
+    pub shots: Vec<ShotRecord>,
+    // Fleets whose every cell has been reported hit, in the order they went down
+    #[serde(default)]
+    pub eliminated: Vec<String>,
+    // Fleets that published their secrets, with the result of the audit
+    #[serde(default)]
+    pub revealed: Vec<RevealedFleet>,
+    // First fleet whose victory claim was accepted
+    #[serde(default)]
+    pub winner: Option<String>,
+    // Version of the guests the game is pinned to
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub scans: Vec<ScanRecord>,
+    // Scans each fleet may request in this game
+    #[serde(default)]
+    pub scan_limit: u32,
+    // Team declared by each fleet that joined with one
+    #[serde(default)]
+    pub teams: BTreeMap<String, String>,
+    // Fleets that left the game, in order
+    #[serde(default)]
+    pub resigned: Vec<ResignRecord>,
+    // Tokens each fleet deposits when it joins
+    #[serde(default)]
+    pub stake: u64,
+    // Stakes held by the game until it is paid out or refunded
+    #[serde(default)]
+    pub escrow: Vec<Escrow>,
+    #[serde(default)]
+    pub settled: bool,
+    // Tournament the game was paired in, and the only fleets that may join it
+    #[serde(default)]
+    pub tournament: Option<String>,
+    #[serde(default)]
+    pub roster: Vec<String>,
+}
+
+// Struct describing a fleet that resigned, with the cells it had afloat if it showed them
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ResignRecord {
+    pub fleet: String,
+    pub remaining: Option<Vec<u8>>,
+}
+
+// Struct describing the stake a fleet deposited from its account
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Escrow {
+    pub fleet: String,
+    pub account: String,
+    pub amount: u64,
+}
+
+impl GameStatus {
+    pub fn teammates(&self, fleet: &str, other: &str) -> bool {
+        teammates(&self.teams, fleet, other)
+    }
+}
+
+// Struct sent by the rust code to publish the secrets a fleet joined with, once the game is over
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RevealData {
+    pub gameid: String,
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub random: String,
+    // Ship moves made during the game, in order
+    #[serde(default)]
+    pub moves: Vec<ShipMove>,
+}
+
+// Struct describing a revealed fleet and every inconsistency found replaying its journals
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RevealedFleet {
+    pub fleet: String,
+    pub board: Vec<u8>,
+    pub issues: Vec<String>,
+}
+
+// Image ID the receipts of a command are verified against
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct MethodId {
+    pub cmd: Command,
+    pub id: Digest,
+}
+
+// Image IDs of one release of the guests
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct MethodVersion {
+    pub version: String,
+    pub image_ids: Vec<MethodId>,
+}
+
+// Struct returned by the blockchain server with the guest versions it accepts.
+// New games are pinned to the first version their join receipt verifies against, trying
+// `current` first, and every later receipt of the game must verify against that version.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct MethodsInfo {
+    pub current: String,
+    pub versions: Vec<MethodVersion>,
+}
+
+// Struct exported by the blockchain server with everything needed to check a game offline:
+// the accepted transactions in order, the image IDs they were verified against, the
+// revealed secrets and the state the server derived from them
+#[derive(Clone, Deserialize, Serialize)]
+pub struct GameArchive {
+    pub gameid: String,
+    #[serde(default)]
+    pub version: String,
+    pub image_ids: Vec<MethodId>,
+    pub transactions: Vec<CommunicationData>,
+    pub reveals: Vec<RevealData>,
+    pub status: GameStatus,
+}
+
+// One accepted transaction as read by the summary guest: the command and the journal of its
+// receipt. The receipt itself is handed to the prover as an assumption.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SummaryStep {
+    pub cmd: Command,
+    pub journal: Vec<u8>,
+}
+
+// Struct sent by the rust code for input on the summary method: every accepted transaction
+// of a game, in order, and the image IDs of the guests that produced them
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SummaryInputs {
+    pub gameid: String,
+    pub image_ids: Vec<MethodId>,
+    pub steps: Vec<SummaryStep>,
+    // Fleet a tournament gave the first turn to, None when the first fleet to join plays first
+    pub first_player: Option<String>,
+}
+
+// Final state of a fleet as committed by the summary method
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FleetSummary {
+    pub fleet: String,
+    pub board: Digest,
+    pub shots: Digest,
+    pub hits: u32,
+}
+
+// Struct to specify the output journal for the summary method
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SummaryJournal {
+    pub gameid: String,
+    pub image_ids: Vec<MethodId>,
+    pub transactions: u32,
+    pub fleets: Vec<FleetSummary>,
+    pub eliminated: Vec<String>,
+    pub winner: Option<String>,
+    pub first_player: Option<String>,
+}
+
+// Struct holding the receipt of the summary method for a game, standing for all its receipts
+#[derive(Clone, Deserialize, Serialize)]
+pub struct GameSummary {
+    pub gameid: String,
+    pub receipt: Receipt,
+}
+
+// Struct describing a finished game: each fleet of the winning side beat each fleet it
+// eliminated. The blockchain server rates it between the accounts the fleets joined with.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct GameResult {
+    pub gameid: String,
+    pub winners: Vec<String>,
+    pub losers: Vec<String>,
+}
+
+// Rating of a ledger account over the rated games its fleets played
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct LeaderboardEntry {
+    pub account: String,
+    pub rating: i32,
+    pub games: u32,
+    pub wins: u32,
+}
+
+// Pairing scheme of a tournament: single elimination, or every fleet meeting every other once
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub enum TournamentFormat {
+    #[default]
+    Bracket,
+    RoundRobin,
+}
+
+// Struct sent to the blockchain server to open a tournament for registration
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TournamentRequest {
+    pub tournament: String,
+    pub format: TournamentFormat,
+}
+
+// Struct sent to the blockchain server to register a fleet in a tournament
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RegisterRequest {
+    pub fleet: String,
+}
+
+// Struct sent to the blockchain server to decide a stalled game of a tournament by forfeit
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ForfeitRequest {
+    pub gameid: String,
+}
+
+// Struct describing the fleets paired in a round of a tournament. A fleet left without an
+// opponent sits the round out: it has no game and advances as the winner.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Pairing {
+    pub gameid: Option<String>,
+    pub fleets: Vec<String>,
+    pub winner: Option<String>,
+}
+
+// Struct returned by the blockchain server with the state of a tournament
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TournamentStatus {
+    pub tournament: String,
+    pub format: TournamentFormat,
+    // Rules every game of the tournament is pinned to
+    pub version: String,
+    pub scan_limit: u32,
+    pub stake: u64,
+    // Registered fleets, in the order they registered
+    pub fleets: Vec<String>,
+    // Rounds started so far, none while registration is open
+    pub rounds: Vec<Vec<Pairing>>,
+    // Games won by each fleet
+    pub wins: BTreeMap<String, u32>,
+    pub champion: Option<String>,
+}
+
+// Struct sent to the blockchain server to fund an account from its faucet
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FaucetRequest {
+    pub account: String,
+}
+
+// Struct returned by the blockchain server with the tokens of an account
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct AccountStatus {
+    pub account: String,
+    pub balance: u64,
+    // Stakes of the account held by games not settled yet
+    pub escrow: u64,
+}
+
+// Ledger account of a secret account key: the hex SHA256 of the key, so that only the
+// holder of the key can stake from it
+pub fn account_id(key: &str) -> String {
+    let hash: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Commitment to a board as computed by the guests: SHA256 over (random || board)
+pub fn board_digest(random: &str, board: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(random.as_bytes());
+    hasher.update(board);
+    let hash: [u8; 32] = hasher.finalize().into();
+    Digest::from_bytes(hash)
+}
+
+// Commitment to the shot history of a fleet, as computed by the fire method. Shots are
+// public, so unlike boards the history needs no salt; each target is length prefixed.
+pub fn shots_digest(shots: &[Shot]) -> Digest {
+    let mut hasher = Sha256::new();
+    for shot in shots {
+        hasher.update((shot.target.len() as u32).to_le_bytes());
+        hasher.update(shot.target.as_bytes());
+        hasher.update([shot.pos]);
+    }
+    let hash: [u8; 32] = hasher.finalize().into();
+    Digest::from_bytes(hash)
+}
+
+// Struct describing a shot registered by the blockchain server and, once reported, its result
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct ShotRecord {
+    pub fleet: String,
+    pub target: String,
+    pub pos: u8,
+    pub report: Option<String>,
+}
+
+// Struct describing a scan registered by the blockchain server and, once answered, its count
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct ScanRecord {
+    pub fleet: String,
+    pub target: String,
+    pub center: u8,
+    pub count: Option<u8>,
+}
+
+// Cells of the 3x3 area around `center`, cut at the edges of the board
+pub fn scan_area(center: u8) -> Vec<u8> {
+    let (x, y) = ((center % 10) as i8, (center / 10) as i8);
+    let mut cells = Vec::new();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (cx, cy) = (x + dx, y + dy);
+            if (0..10).contains(&cx) && (0..10).contains(&cy) {
+                cells.push((cy * 10 + cx) as u8);
+            }
+        }
+    }
+    cells
+}
+
+// Struct describing one accepted transaction of a game as replayed by the blockchain server:
+// the public values of its journal and whether its receipt still verifies
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ReplayStep {
+    pub cmd: Command,
+    pub fleet: String,
+    pub target: Option<String>,
+    pub pos: Option<u8>,
+    pub report: Option<String>,
+    // Positions and reports of a salvo
+    #[serde(default)]
+    pub positions: Vec<u8>,
+    #[serde(default)]
+    pub reports: Vec<String>,
+    // Ship cells counted by a scan, whose center is in `pos`
+    #[serde(default)]
+    pub count: Option<u8>,
+    // Team declared by a joining fleet
+    #[serde(default)]
+    pub team: Option<String>,
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+// Whether two fleets declared the same team in a map of fleet to team; a fleet is not its
+// own teammate
+pub fn teammates(teams: &BTreeMap<String, String>, fleet: &str, other: &str) -> bool {
+    fleet != other
+        && teams
+            .get(fleet)
+            .is_some_and(|team| teams.get(other) == Some(team))
+}
+
+// Ship sizes of a complete fleet, smallest first
+pub const FLEET_SHIPS: [usize; 7] = [1, 1, 2, 2, 3, 4, 5];
+
+// Fleet rules shared by the join guest and the host: 18 distinct cells forming
+// one carrier (5), one battleship (4), one destroyer (3), two cruisers (2) and two submarines (1)
+pub fn is_valid_fleet(board: &[u8]) -> bool {
+    check_fleet(board).is_ok()
+}
+
+// Same rules as is_valid_fleet, explaining why a placement is rejected
+pub fn check_fleet(board: &[u8]) -> Result<(), String> {
+    // --- Simple debug flag for quick testing ---
+    const DEBUG_ALLOW_3_SHIP: bool = false; // Set to true for debug mode
+
+    if DEBUG_ALLOW_3_SHIP && board.len() == 3 {
+        check_cells(board)?;
+        // Check if the 3 cells are contiguous horizontally or vertically
+        let mut sorted = board.to_vec();
+        sorted.sort_unstable();
+        // Horizontal
+        if sorted[1] == sorted[0] + 1
+            && sorted[2] == sorted[1] + 1
+            && sorted[0] / 10 == sorted[1] / 10
+            && sorted[1] / 10 == sorted[2] / 10
+        {
+            return Ok(());
+        }
+        // Vertical
+        if sorted[1] == sorted[0] + 10
+            && sorted[2] == sorted[1] + 10
+            && sorted[0] % 10 == sorted[1] % 10
+            && sorted[1] % 10 == sorted[2] % 10
+        {
+            return Ok(());
+        }
+        return Err("The debug ship must be 3 contiguous cells in a line".to_string());
+    }
+    // --- End debug flag ---
+
+    if board.len() != FLEET_CELLS {
+        return Err(format!(
+            "A fleet occupies {} cells, but {} were given",
+            FLEET_CELLS,
+            board.len()
+        ));
+    }
+    check_cells(board)?;
+
+    let mut sizes: Vec<usize> = fleet_ships(board).iter().map(|ship| ship.len()).collect();
+    sizes.sort_unstable();
+    if sizes != FLEET_SHIPS {
+        let found: Vec<String> = sizes.iter().map(|s| s.to_string()).collect();
+        return Err(format!(
+            "Ships must have sizes 1, 1, 2, 2, 3, 4 and 5, but the cells form ships of sizes {}",
+            found.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+// Split a placement into ships: runs of cells along a row, or else along a column
+pub fn fleet_ships(board: &[u8]) -> Vec<Vec<u8>> {
+    let mut positions = board.to_vec();
+    positions.sort_unstable();
+
+    let mut ships = Vec::new();
+    let mut used = vec![false; positions.len()];
+
+    for idx in 0..positions.len() {
+        if used[idx] {
+            continue;
+        }
+        let start = positions[idx];
+        let mut ship = vec![start];
+        used[idx] = true;
+
+        // Try to grow horizontally
+        let mut next = start + 1;
+        while let Some(pos_idx) = positions.iter().enumerate().find_map(|(k, &p)| {
+            if p == next && !used[k] {
+                Some(k)
+            } else {
+                None
+            }
+        }) {
+            // Ensure same row
+            if next / 10 != start / 10 {
+                break;
+            }
+            ship.push(next);
+            used[pos_idx] = true;
+            next += 1;
+        }
+
+        // If only one position, try vertical
+        if ship.len() == 1 {
+            let mut next = start + 10;
+            while let Some(pos_idx) = positions.iter().enumerate().find_map(|(k, &p)| {
+                if p == next && !used[k] {
+                    Some(k)
+                } else {
+                    None
+                }
+            }) {
+                // Ensure same column
+                if next % 10 != start % 10 {
+                    break;
+                }
+                ship.push(next);
+                used[pos_idx] = true;
+                next += 10;
+            }
+        }
+
+        // If still only one position, it's a submarine
+        ships.push(ship);
+    }
+
+    ships
+}
+
+// Number of ships of the joined placement with at least one cell left on the board
+pub fn ships_afloat(joined: &[u8], board: &[u8]) -> usize {
+    fleet_ships(joined)
+        .iter()
+        .filter(|ship| ship.iter().any(|cell| board.contains(cell)))
+        .count()
+}
+
+// Board after moving the ship holding `cell` one cell in `direction`, sorted. Only an
+// undamaged ship may move, it must stay on the board and off every cell already shot at,
+// and, with the cells hit so far, the fleet must still follow the fleet rules.
+pub fn moved_board(
+    board: &[u8],
+    hits: &[u8],
+    misses: &[u8],
+    ship_move: &ShipMove,
+) -> Result<Vec<u8>, String> {
+    let mut fleet = board.to_vec();
+    fleet.extend_from_slice(hits);
+    check_fleet(&fleet)
+        .map_err(|err| format!("The board and its hits are not a fleet: {}", err))?;
+
+    let ship = fleet_ships(&fleet)
+        .into_iter()
+        .find(|ship| ship.contains(&ship_move.cell))
+        .ok_or_else(|| format!("No ship at position {}", ship_move.cell))?;
+    if ship.iter().any(|cell| hits.contains(cell)) {
+        return Err("Only an undamaged ship can move".to_string());
+    }
+
+    let mut next_board: Vec<u8> = board
+        .iter()
+        .copied()
+        .filter(|c| !ship.contains(c))
+        .collect();
+    for &cell in &ship {
+        let (x, y) = (cell % 10, cell / 10);
+        let moved = match ship_move.direction {
+            Direction::Up if y > 0 => cell - 10,
+            Direction::Down if y < 9 => cell + 10,
+            Direction::Left if x > 0 => cell - 1,
+            Direction::Right if x < 9 => cell + 1,
+            _ => return Err("The ship would leave the board".to_string()),
+        };
+        if hits.contains(&moved) || misses.contains(&moved) {
+            return Err(format!("The ship would move onto the shot cell {}", moved));
+        }
+        next_board.push(moved);
+    }
+    next_board.sort_unstable();
+
+    let mut fleet = next_board.clone();
+    fleet.extend_from_slice(hits);
+    check_fleet(&fleet).map_err(|err| format!("The moved ship breaks the fleet rules: {}", err))?;
+    Ok(next_board)
+}
+
+// Every cell must be on the 10x10 board and appear only once
+fn check_cells(board: &[u8]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for &pos in board {
+        if pos > 99 {
+            return Err(format!("Cell {} is outside the 10x10 board (0-99)", pos));
+        }
+        if !seen.insert(pos) {
+            return Err(format!("Cell {} is used more than once", pos));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Carrier along row 0, the cruiser at 50 lying under the destroyer, and submarines at 9 and 83
+    const FLEET: [u8; FLEET_CELLS] = [
+        0, 1, 2, 3, 4, 9, 20, 21, 22, 23, 40, 41, 42, 50, 51, 80, 81, 83,
+    ];
+
+    fn ship_move(cell: u8, direction: Direction) -> ShipMove {
+        ShipMove { cell, direction }
+    }
+
+    fn without(cells: &[u8]) -> Vec<u8> {
+        FLEET
+            .iter()
+            .copied()
+            .filter(|c| !cells.contains(c))
+            .collect()
+    }
+
+    #[test]
+    fn moves_an_undamaged_ship() {
+        let board = moved_board(&FLEET, &[], &[], &ship_move(83, Direction::Down)).unwrap();
+        let mut expected = without(&[83]);
+        expected.push(93);
+        assert_eq!(board, expected);
+
+        let board = moved_board(&FLEET, &[], &[], &ship_move(21, Direction::Right)).unwrap();
+        let mut expected = without(&[20]);
+        expected.push(24);
+        expected.sort_unstable();
+        assert_eq!(board, expected);
+    }
+
+    #[test]
+    fn rejects_leaving_the_board() {
+        let err = moved_board(&FLEET, &[], &[], &ship_move(9, Direction::Right)).unwrap_err();
+        assert_eq!(err, "The ship would leave the board");
+        let err = moved_board(&FLEET, &[], &[], &ship_move(2, Direction::Up)).unwrap_err();
+        assert_eq!(err, "The ship would leave the board");
+    }
+
+    #[test]
+    fn rejects_overlapping_another_ship() {
+        let err = moved_board(&FLEET, &[], &[], &ship_move(51, Direction::Up)).unwrap_err();
+        assert!(
+            err.starts_with("The moved ship breaks the fleet rules"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_joining_an_adjacent_ship() {
+        // The submarine would extend the cruiser at 80 into a ship of size 3
+        let err = moved_board(&FLEET, &[], &[], &ship_move(83, Direction::Left)).unwrap_err();
+        assert!(
+            err.starts_with("The moved ship breaks the fleet rules"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_moving_a_hit_ship() {
+        let err =
+            moved_board(&without(&[4]), &[4], &[], &ship_move(0, Direction::Down)).unwrap_err();
+        assert_eq!(err, "Only an undamaged ship can move");
+    }
+
+    #[test]
+    fn rejects_moving_onto_a_shot_cell() {
+        let err = moved_board(&FLEET, &[], &[93], &ship_move(83, Direction::Down)).unwrap_err();
+        assert_eq!(err, "The ship would move onto the shot cell 93");
+        let err =
+            moved_board(&without(&[41]), &[41], &[], &ship_move(50, Direction::Up)).unwrap_err();
+        assert_eq!(err, "The ship would move onto the shot cell 41");
+    }
+
+    #[test]
+    fn rejects_an_empty_cell() {
+        let err = moved_board(&FLEET, &[], &[], &ship_move(55, Direction::Up)).unwrap_err();
+        assert_eq!(err, "No ship at position 55");
+    }
+}
@@ -0,0 +1,503 @@
+// Board editor and game view for the host page.
+// PAGE is set by the template with the values of the last submission.
+
+const SHIP_SIZES = [5, 4, 3, 2, 2, 1, 1];
+
+// Ships placed on my grid, each an array of cells (0-99)
+let ships = [];
+let selectedSize = 5;
+let horizontal = true;
+// Editing stops once the fleet is committed to a game
+const locked = PAGE.ok && PAGE.gameid !== '' && PAGE.fleetid !== '';
+// Last game state received from the chain
+let gameState = null;
+// Action each stored game waits on from its local fleet, keyed by game and fleet
+const pending = {};
+const pageTitle = document.title;
+
+const fleetGrid = document.getElementById('fleet-grid');
+const targetGrid = document.getElementById('target-grid');
+const fleetCells = buildGrid(fleetGrid);
+const targetCells = buildGrid(targetGrid);
+
+function label(pos) {
+    return String.fromCharCode(65 + pos % 10) + Math.floor(pos / 10);
+}
+
+function buildGrid(container) {
+    const cells = [];
+    const corner = document.createElement('div');
+    corner.classList.add('cell_empty');
+    container.appendChild(corner);
+    for (let i = 0; i < 10; i++) {
+        const cell = document.createElement('div');
+        cell.classList.add('cell_x_label');
+        cell.textContent = String.fromCharCode(65 + i);
+        container.appendChild(cell);
+    }
+    for (let i = 0; i < 100; i++) {
+        if (i % 10 === 0) {
+            const cell = document.createElement('div');
+            cell.classList.add('cell_y_label');
+            cell.textContent = i / 10;
+            container.appendChild(cell);
+        }
+        const cell = document.createElement('div');
+        cell.classList.add('cell');
+        cell.dataset.pos = i;
+        container.appendChild(cell);
+        cells.push(cell);
+    }
+    return cells;
+}
+
+function parseCells(text) {
+    return decodeURIComponent(text || '')
+        .split(',')
+        .filter(s => s.trim() !== '')
+        .map(s => parseInt(s, 10))
+        .filter(n => !isNaN(n));
+}
+
+// Split cells into ships the same way the join guest does:
+// grow horizontally from the smallest free cell, then vertically
+function decompose(cells) {
+    const sorted = [...cells].sort((a, b) => a - b);
+    const used = new Set();
+    const result = [];
+    for (const start of sorted) {
+        if (used.has(start)) {
+            continue;
+        }
+        const ship = [start];
+        used.add(start);
+        let next = start + 1;
+        while (sorted.includes(next) && !used.has(next) && Math.floor(next / 10) === Math.floor(start / 10)) {
+            ship.push(next);
+            used.add(next);
+            next += 1;
+        }
+        if (ship.length === 1) {
+            next = start + 10;
+            while (sorted.includes(next) && !used.has(next)) {
+                ship.push(next);
+                used.add(next);
+                next += 10;
+            }
+        }
+        result.push(ship);
+    }
+    return result;
+}
+
+function shipCells() {
+    return ships.flat().sort((a, b) => a - b);
+}
+
+// Sizes from the palette that are not on the grid yet
+function remainingSizes() {
+    const remaining = [...SHIP_SIZES];
+    for (const ship of ships) {
+        const idx = remaining.indexOf(ship.length);
+        if (idx >= 0) {
+            remaining.splice(idx, 1);
+        }
+    }
+    return remaining;
+}
+
+// Cells covered by a ship of the selected size starting at pos, or null when off the board
+function footprint(pos) {
+    const x = pos % 10;
+    const y = Math.floor(pos / 10);
+    if ((horizontal && x + selectedSize > 10) || (!horizontal && y + selectedSize > 10)) {
+        return null;
+    }
+    const cells = [];
+    for (let i = 0; i < selectedSize; i++) {
+        cells.push(horizontal ? pos + i : pos + 10 * i);
+    }
+    return cells;
+}
+
+function renderPalette() {
+    const palette = document.getElementById('palette');
+    palette.querySelectorAll('.ship-choice').forEach(button => button.remove());
+    const remaining = remainingSizes();
+    if (!remaining.includes(selectedSize) && remaining.length > 0) {
+        selectedSize = remaining[0];
+    }
+    const distinct = [...new Set(SHIP_SIZES)];
+    for (const size of distinct) {
+        const button = document.createElement('button');
+        button.type = 'button';
+        button.classList.add('ship-choice');
+        const left = remaining.filter(s => s === size).length;
+        button.textContent = size + ' (' + left + ')';
+        button.disabled = locked || left === 0;
+        if (size === selectedSize && left > 0) {
+            button.classList.add('active');
+        }
+        button.addEventListener('click', () => {
+            selectedSize = size;
+            renderPalette();
+        });
+        palette.insertBefore(button, palette.firstChild);
+    }
+    ['rotate', 'random', 'clear'].forEach(id => document.getElementById(id).disabled = locked);
+}
+
+function shotsAt(target) {
+    return gameState ? gameState.shots.filter(shot => shot.target === target) : [];
+}
+
+function markShot(cell, shot) {
+    if (shot.report === 'Hit') {
+        cell.classList.add('hit');
+    } else if (shot.report === 'Miss') {
+        cell.classList.add('miss');
+    } else {
+        cell.classList.add('pending');
+    }
+}
+
+function renderFleet() {
+    const cells = new Set(shipCells());
+    fleetCells.forEach((cell, pos) => {
+        cell.className = 'cell';
+        if (cells.has(pos)) {
+            cell.classList.add('ship');
+        }
+    });
+    for (const shot of shotsAt(PAGE.fleetid)) {
+        markShot(fleetCells[shot.pos], shot);
+    }
+}
+
+// Positions typed or clicked for the next salvo
+function salvoPositions() {
+    return document.getElementById('salvo').value
+        .split(',')
+        .map(s => s.trim().toUpperCase())
+        .filter(s => s !== '');
+}
+
+function salvoMode() {
+    return gameState !== null && gameState.mode === 'Salvo';
+}
+
+function renderTarget() {
+    const target = document.getElementById('targetfleet').value;
+    const selected = document.getElementById('x').value + document.getElementById('y').value;
+    const salvo = salvoPositions();
+    targetCells.forEach((cell, pos) => {
+        cell.className = 'cell';
+        if (salvoMode() ? salvo.includes(label(pos)) : label(pos) === selected) {
+            cell.classList.add('selected');
+        }
+    });
+    if (!gameState) {
+        return;
+    }
+    gameState.shots
+        .filter(shot => shot.fleet === PAGE.fleetid && shot.target === target)
+        .forEach(shot => markShot(targetCells[shot.pos], shot));
+}
+
+// Ask the host whether the fleet satisfies the same rules as the join guest
+function validate() {
+    const status = document.getElementById('fleet-status');
+    if (locked) {
+        status.textContent = '';
+        return;
+    }
+    fetch('/validate?board=' + shipCells().join(','))
+        .then(response => response.json())
+        .then(result => {
+            status.className = result.ok ? 'status-ok' : 'status-error';
+            status.textContent = result.ok ? 'Fleet is valid' : result.reason;
+        });
+}
+
+function changed() {
+    renderPalette();
+    renderFleet();
+    validate();
+}
+
+fleetCells.forEach((cell, pos) => {
+    cell.addEventListener('mouseenter', () => {
+        if (locked || remainingSizes().length === 0) {
+            return;
+        }
+        const cells = footprint(pos);
+        const occupied = new Set(shipCells());
+        const blocked = !cells || cells.some(c => occupied.has(c));
+        (cells || [pos]).forEach(c => fleetCells[c].classList.add(blocked ? 'blocked' : 'preview'));
+    });
+    cell.addEventListener('mouseleave', () => {
+        fleetCells.forEach(c => c.classList.remove('preview', 'blocked'));
+    });
+    cell.addEventListener('click', () => {
+        if (locked) {
+            // Once joined, a click picks the ship to move
+            if (shipCells().includes(pos)) {
+                document.getElementById('ship').value = label(pos);
+            }
+            return;
+        }
+        const existing = ships.findIndex(ship => ship.includes(pos));
+        if (existing >= 0) {
+            ships.splice(existing, 1);
+        } else if (remainingSizes().includes(selectedSize)) {
+            const cells = footprint(pos);
+            const occupied = new Set(shipCells());
+            if (!cells || cells.some(c => occupied.has(c))) {
+                return;
+            }
+            ships.push(cells);
+        }
+        changed();
+    });
+});
+
+targetCells.forEach((cell, pos) => {
+    cell.addEventListener('click', () => {
+        // The fire guest refuses a second shot at the same cell of a fleet
+        const target = document.getElementById('targetfleet').value;
+        if (shotsAt(target).some(shot => shot.fleet === PAGE.fleetid && shot.pos === pos)) {
+            return;
+        }
+        const text = label(pos);
+        if (salvoMode()) {
+            // Clicking a selected cell takes it out of the salvo
+            const salvo = salvoPositions();
+            const idx = salvo.indexOf(text);
+            if (idx >= 0) {
+                salvo.splice(idx, 1);
+            } else {
+                salvo.push(text);
+            }
+            document.getElementById('salvo').value = salvo.join(',');
+        } else {
+            document.getElementById('x').value = text[0];
+            document.getElementById('y').value = text.substring(1);
+        }
+        renderTarget();
+    });
+});
+
+document.getElementById('targetfleet').addEventListener('change', renderTarget);
+document.getElementById('salvo').addEventListener('input', renderTarget);
+
+document.getElementById('rotate').addEventListener('click', () => {
+    horizontal = !horizontal;
+});
+
+document.addEventListener('keydown', event => {
+    if (event.key === 'r' && event.target.tagName !== 'INPUT') {
+        horizontal = !horizontal;
+    }
+});
+
+document.getElementById('clear').addEventListener('click', () => {
+    ships = [];
+    changed();
+});
+
+// Replace the fleet with a random valid placement from the host
+document.getElementById('random').addEventListener('click', () => {
+    fetch('/random-fleet')
+        .then(response => response.text())
+        .then(text => {
+            ships = decompose(parseCells(text));
+            changed();
+        });
+});
+
+// Whether two fleets declared the same team when joining
+function teammates(state, fleet, other) {
+    const teams = state.teams || {};
+    return fleet !== other && teams[fleet] !== undefined && teams[fleet] === teams[other];
+}
+
+// Show the proven state of the game as recorded on the chain
+function applyGameState(state) {
+    gameState = state;
+    const select = document.getElementById('targetfleet');
+    const current = select.value;
+    select.innerHTML = '';
+    state.players.filter(p => p !== PAGE.fleetid && !teammates(state, p, PAGE.fleetid)).forEach(player => {
+        const option = document.createElement('option');
+        option.value = player;
+        option.textContent = player;
+        select.appendChild(option);
+    });
+    if ([...select.options].some(o => o.value === current)) {
+        select.value = current;
+    }
+
+    const turn = document.getElementById('turn');
+    const notice = document.getElementById('notice');
+    notice.textContent = '';
+    if (state.next_player) {
+        if (state.next_player === PAGE.fleetid) {
+            turn.textContent = 'Your turn to play';
+        } else if (teammates(state, state.next_player, PAGE.fleetid)) {
+            turn.textContent = 'Your team\'s turn to play';
+        } else {
+            turn.textContent = 'Waiting for ' + state.next_player;
+        }
+    } else if (state.next_report) {
+        turn.textContent = 'Waiting for ' + state.next_report + ' to report';
+    }
+    const salvo = salvoMode() ? shotsAt(PAGE.fleetid).filter(s => s.report === null) : [];
+    const scan = (state.scans || []).find(s => s.target === PAGE.fleetid && s.count === null);
+    if (state.next_report === PAGE.fleetid && scan) {
+        notice.textContent = 'You were scanned around ' + label(scan.center) + ': answer the scan';
+    } else if (state.next_report === PAGE.fleetid && salvo.length > 0) {
+        notice.textContent = 'You were shot at ' + salvo.map(s => label(s.pos)).join(', ') + ': report the salvo';
+    } else if (state.next_report === PAGE.fleetid && state.last_shot_pos !== null) {
+        const text = label(state.last_shot_pos);
+        document.getElementById('rx').value = text[0];
+        document.getElementById('ry').value = text.substring(1);
+        document.getElementById('report').value = shipCells().includes(state.last_shot_pos) ? 'Hit' : 'Miss';
+        notice.textContent = 'You were shot at ' + text + ': report the result';
+    }
+    renderScans(state);
+    renderFleet();
+    renderTarget();
+}
+
+// Results of the scans this fleet requested, and how many it has left
+function renderScans(state) {
+    const scans = (state.scans || []).filter(s => s.fleet === PAGE.fleetid);
+    const results = scans.map(s => label(s.center) + ' on ' + s.target + ': ' +
+        (s.count === null ? 'awaiting answer' : s.count + ' ship cells'));
+    const left = Math.max(0, (state.scan_limit || 0) - scans.length);
+    document.getElementById('scans').textContent =
+        (results.length > 0 ? 'Scans: ' + results.join(', ') + '. ' : '') + left + ' scans left';
+}
+
+function refreshGame() {
+    if (!PAGE.gameid) {
+        return;
+    }
+    fetch('/api/games/' + encodeURIComponent(PAGE.gameid))
+        .then(response => response.ok ? response.json() : null)
+        .then(state => {
+            if (state) {
+                applyGameState(state);
+            }
+        });
+}
+
+// Action the chain waits for from the given fleet, if any
+function pendingAction(state, fleetid) {
+    if (state.next_report === fleetid && (state.scans || []).some(s => s.target === fleetid && s.count === null)) {
+        return 'Answer the scan received';
+    }
+    if (state.next_report === fleetid && state.mode === 'Salvo') {
+        return 'Report the salvo received';
+    }
+    if (state.next_report === fleetid) {
+        return 'Report the shot at ' + label(state.last_shot_pos);
+    }
+    if (state.next_player === fleetid) {
+        return 'Your turn to fire';
+    }
+    if (state.next_player && teammates(state, state.next_player, fleetid)) {
+        return 'Your team\'s turn to fire';
+    }
+    return null;
+}
+
+// Mark a stored game in the switcher and notify once when it starts waiting on its fleet
+function updateSession(item, state) {
+    const key = item.dataset.game + '/' + item.dataset.fleet;
+    const message = pendingAction(state, item.dataset.fleet);
+    item.querySelector('.pending-action').textContent = message ? '\u25CF ' + message : '';
+    if (message && pending[key] !== message) {
+        const current = item.dataset.game === PAGE.gameid && item.dataset.fleet === PAGE.fleetid;
+        if ('Notification' in window && Notification.permission === 'granted' && (document.hidden || !current)) {
+            new Notification('Game ' + item.dataset.game, { body: item.dataset.fleet + ': ' + message });
+        }
+    }
+    pending[key] = message;
+    document.title = Object.values(pending).some(m => m) ? '(!) ' + pageTitle : pageTitle;
+}
+
+// Refresh the pending actions of every stored game
+function refreshSessions() {
+    document.querySelectorAll('#sessions li[data-game]').forEach(item => {
+        fetch('/api/games/' + encodeURIComponent(item.dataset.game))
+            .then(response => response.ok ? response.json() : null)
+            .then(state => {
+                if (state) {
+                    updateSession(item, state);
+                }
+            });
+    });
+}
+
+function logActivity(text) {
+    const list = document.getElementById('activity');
+    const item = document.createElement('li');
+    item.textContent = new Date().toLocaleTimeString() + ' ' + text;
+    list.insertBefore(item, list.firstChild);
+    while (list.children.length > 50) {
+        list.removeChild(list.lastChild);
+    }
+}
+
+// Chain messages name games as "game <id>"
+function mentions(message, gameid) {
+    return new RegExp('game ' + gameid.replace(/[.*+?^${}()|[\]\\]/g, '\\$&') + '(?![\\w-])').test(message);
+}
+
+// Follow the transactions of the stored games as the chain accepts them and refresh the
+// view of the games they touch
+function followGames() {
+    const items = [...document.querySelectorAll('#sessions li[data-game]')];
+    if ((!PAGE.gameid && items.length === 0) || !window.EventSource) {
+        return;
+    }
+    if ('Notification' in window && Notification.permission === 'default') {
+        Notification.requestPermission();
+    }
+    const events = new EventSource('/events');
+    events.onmessage = event => {
+        if (PAGE.gameid && mentions(event.data, PAGE.gameid)) {
+            logActivity(event.data);
+            refreshGame();
+        }
+        if (items.some(item => mentions(event.data, item.dataset.game))) {
+            refreshSessions();
+        }
+    };
+    events.addEventListener('chain-error', event => logActivity('Lost the chain: ' + event.data));
+}
+
+// The board proven from now on excludes the cells already hit
+function provenBoard() {
+    const hits = new Set(shotsAt(PAGE.fleetid).filter(s => s.report === 'Hit').map(s => s.pos));
+    return shipCells().filter(pos => !hits.has(pos));
+}
+
+document.querySelector('form').addEventListener('submit', event => {
+    document.body.style.cursor = 'wait';
+    const form = event.target;
+    form.querySelector('#board').value = encodeURIComponent(provenBoard().join(','));
+    setTimeout(() => {
+        form.querySelectorAll('button[type="submit"]').forEach(button => button.disabled = true);
+    }, 10);
+});
+
+ships = decompose(parseCells(PAGE.board));
+if (locked) {
+    fleetGrid.classList.add('locked');
+}
+changed();
+refreshGame();
+refreshSessions();
+followGames();
@@ -0,0 +1,965 @@
+// Command line client for the battleship game.
+// Every action proves locally, exactly like the web interface, and the fleet
+// secrets are kept in the local board store between invocations.
+use clap::{Args, Parser, Subcommand};
+use nanoid::nanoid;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use fleetcore::{account_id, moved_board, Command, GameMode, TournamentFormat, TournamentStatus};
+use host::bot::{run_bot, BotOptions};
+use host::config::{self, HostOptions};
+use host::fleet::random_fleet;
+use host::store::{BoardStore, FleetSecrets};
+use host::{
+    account_status, answer_scan, create_tournament, discard_receipt, export_game, faucet, fire,
+    follow_logs, forfeit, game_status, hits_on, join_game, leaderboard, mentions_game, move_ship,
+    parse_position, pending_receipt, pending_receipts, position_label, prove_summary,
+    publish_summary, refund, register_fleet, report, reports_on, resign, resubmit, reveal, salvo,
+    salvo_report, scan, start_tournament, tournament_status, unmarshal_move, wave, win, FormData,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "segcom",
+    version,
+    about = "Command line client for the battleship game"
+)]
+struct Cli {
+    #[command(flatten)]
+    options: HostOptions,
+    /// Print results as JSON, one object per line
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Args)]
+struct FleetArgs {
+    /// Game ID
+    #[arg(long)]
+    game: String,
+    /// Your fleet's ID
+    #[arg(long)]
+    fleet: String,
+}
+
+#[derive(Subcommand)]
+enum TournamentAction {
+    /// Open a tournament for registration
+    Create {
+        /// Tournament ID
+        #[arg(long)]
+        tournament: String,
+        /// Pair every fleet with every other instead of eliminating the losers
+        #[arg(long)]
+        round_robin: bool,
+    },
+    /// Register a fleet before the tournament starts
+    Register {
+        /// Tournament ID
+        #[arg(long)]
+        tournament: String,
+        /// Fleet ID
+        #[arg(long)]
+        fleet: String,
+    },
+    /// Close the registration and pair the first round
+    Start {
+        /// Tournament ID
+        #[arg(long)]
+        tournament: String,
+    },
+    /// Decide a game of the current round stalled past the refund timeout by forfeit
+    Forfeit {
+        /// Tournament ID
+        #[arg(long)]
+        tournament: String,
+        /// Game ID
+        #[arg(long)]
+        game: String,
+    },
+    /// Show the rounds of a tournament and the games to join
+    Status {
+        /// Tournament ID
+        #[arg(long)]
+        tournament: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Join a game, committing to a board placement
+    Join {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Comma separated board cells (0-99); defaults to the stored board
+        #[arg(long)]
+        board: Option<String>,
+        /// Place the fleet at random instead of using a given or stored board
+        #[arg(long, conflicts_with = "board")]
+        random_fleet: bool,
+        /// Random salt for the board commitment; generated when missing
+        #[arg(long)]
+        random: Option<String>,
+        /// Team to play in; its fleets share their turns and cannot fire at each other
+        #[arg(long)]
+        team: Option<String>,
+    },
+    /// Fire a shot at another fleet
+    Fire {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Fleet to fire at
+        #[arg(long)]
+        target: String,
+        /// Position to fire at, e.g. B3
+        #[arg(long)]
+        at: String,
+    },
+    /// Fire one shot per ship afloat at another fleet; the first shot of a game decides
+    /// whether it is played with salvos
+    Salvo {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Fleet to fire at
+        #[arg(long)]
+        target: String,
+        /// Comma separated positions to fire at, e.g. B3,C4
+        #[arg(long)]
+        at: String,
+    },
+    /// Report the result of the shot received, or of every shot of the salvo received
+    Report {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Position of the shot; defaults to the last shot recorded by the chain
+        #[arg(long)]
+        at: Option<String>,
+    },
+    /// Count the ship cells of another fleet in the 3x3 area around a position, using the turn
+    Scan {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Fleet to scan
+        #[arg(long)]
+        target: String,
+        /// Center of the area, e.g. B3
+        #[arg(long)]
+        at: String,
+    },
+    /// Prove the number of ship cells around the scan received
+    AnswerScan {
+        #[command(flatten)]
+        fleet: FleetArgs,
+    },
+    /// Move an undamaged ship by one cell instead of firing
+    Move {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// A cell of the ship to move, e.g. B3
+        #[arg(long)]
+        at: String,
+        /// Direction to move in: up, down, left or right
+        #[arg(long)]
+        direction: String,
+    },
+    /// Pass the turn on
+    Wave {
+        #[command(flatten)]
+        fleet: FleetArgs,
+    },
+    /// Claim victory
+    Win {
+        #[command(flatten)]
+        fleet: FleetArgs,
+    },
+    /// Leave the game for good, handing the turn on
+    Resign {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Show the cells still afloat in the resign journal
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Publish the stored board and salt so the chain can audit every report
+    Reveal {
+        #[command(flatten)]
+        fleet: FleetArgs,
+    },
+    /// Show the ratings of the fleets that played a game whose summary was published
+    Leaderboard,
+    /// Show the ledger account of the configured account key and its tokens
+    Account {
+        /// Print a new random account key to configure instead
+        #[arg(long)]
+        new: bool,
+    },
+    /// Top the configured account up from the chain's faucet
+    Faucet,
+    /// Give back the stakes of a game nobody won, once every fleet is out or it was abandoned
+    Refund {
+        /// Game ID
+        #[arg(long)]
+        game: String,
+    },
+    /// Run a tournament whose games the chain pairs round by round
+    Tournament {
+        #[command(subcommand)]
+        action: TournamentAction,
+    },
+    /// Show the state of a game
+    Status {
+        /// Game ID
+        #[arg(long)]
+        game: String,
+        /// Local fleet whose stored board should be summarised
+        #[arg(long)]
+        fleet: Option<String>,
+    },
+    /// Save a game with all its receipts, to be checked offline with verify-archive
+    Export {
+        /// Game ID
+        #[arg(long)]
+        game: String,
+        /// File to write the archive to
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Prove a single receipt for every transaction of a game, to be checked with verify-summary
+    Summary {
+        /// Game ID
+        #[arg(long)]
+        game: String,
+        /// File to write the summary to
+        #[arg(long)]
+        out: PathBuf,
+        /// Also publish the summary on the chain
+        #[arg(long)]
+        publish: bool,
+    },
+    /// Let the computer play a fleet until the game is decided
+    Bot {
+        #[command(flatten)]
+        fleet: FleetArgs,
+        /// Seconds between two looks at the game state
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+        /// Fire salvos when the bot fires the first shot of the game
+        #[arg(long)]
+        salvo: bool,
+        /// Team to join the game in
+        #[arg(long)]
+        team: Option<String>,
+    },
+    /// List the receipts the chain has not accepted yet
+    Pending {
+        /// Only list receipts of this game
+        #[arg(long)]
+        game: Option<String>,
+    },
+    /// Submit a pending receipt again without proving again
+    Resubmit {
+        /// Key of the receipt, as listed by pending
+        key: String,
+    },
+    /// Drop a pending receipt
+    Discard {
+        /// Key of the receipt, as listed by pending
+        key: String,
+    },
+    /// Follow the transactions registered by the chain
+    Watch {
+        /// Only show transactions of this game
+        #[arg(long)]
+        game: Option<String>,
+    },
+}
+
+// Result of an action, as printed to the user
+#[derive(Serialize)]
+struct Outcome {
+    command: &'static str,
+    gameid: String,
+    fleet: String,
+    ok: bool,
+    message: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let config = match cli.options.resolve() {
+        Ok(config) => config,
+        Err(err) => fail(cli.json, &err),
+    };
+    config::init(config);
+
+    // Proving is slow: make sure the chain accepts the receipts before starting
+    if matches!(
+        cli.command,
+        Commands::Join { .. }
+            | Commands::Fire { .. }
+            | Commands::Salvo { .. }
+            | Commands::Report { .. }
+            | Commands::Scan { .. }
+            | Commands::AnswerScan { .. }
+            | Commands::Move { .. }
+            | Commands::Wave { .. }
+            | Commands::Win { .. }
+            | Commands::Resign { .. }
+            | Commands::Bot { .. }
+    ) {
+        if let Err(err) = host::check_methods().await {
+            fail(cli.json, &err);
+        }
+    }
+
+    let mut store = match BoardStore::open(&config::get().store) {
+        Ok(store) => store,
+        Err(err) => fail(cli.json, &err),
+    };
+
+    let outcome = match cli.command {
+        Commands::Join {
+            fleet,
+            board,
+            random_fleet: random_placement,
+            random,
+            team,
+        } => {
+            let stored = store.get(&fleet.game, &fleet.fleet).cloned();
+            let board = match board {
+                Some(board) => split_cells(&board).unwrap_or_else(|err| fail(cli.json, &err)),
+                None if random_placement => random_fleet(&mut rand::rng()),
+                // Joining again commits the board joined with, not what is left of it
+                None => match &stored {
+                    Some(stored) if !stored.joined.is_empty() => stored.joined.clone(),
+                    Some(stored) => stored.board.clone(),
+                    None => fail(cli.json, "You must provide a Board Placement"),
+                },
+            };
+            let random = random
+                .or_else(|| stored.as_ref().map(|s| s.random.clone()))
+                .unwrap_or_else(|| nanoid!(12));
+            // The chain answers OK to a fleet joining twice, but keeps the first commitment
+            if stored
+                .as_ref()
+                .is_some_and(|stored| !stored.same_join(&board, &random))
+            {
+                fail(
+                    cli.json,
+                    &format!(
+                        "Fleet {} already joined game {} with other secrets; run join without \
+                         --board and --random to reuse them",
+                        fleet.fleet, fleet.game
+                    ),
+                );
+            }
+            let data = FormData {
+                team,
+                ..FormData::for_fleet(&fleet.game, &fleet.fleet, &board, &random)
+            };
+            let message = join_game(data).await;
+            // Keep the secrets of a join whose receipt is kept for resubmission as well,
+            // so that running join again reuses the receipt
+            if stored.is_none() && (message == "OK" || join_pending(&fleet)) {
+                store.insert(&fleet.game, &fleet.fleet, FleetSecrets::new(board, random));
+                save(&store, cli.json);
+            }
+            outcome("join", &fleet, message)
+        }
+        Commands::Fire { fleet, target, at } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            let (x, y) = split_position(&at, cli.json);
+            data.targetfleet = Some(target);
+            data.x = Some(x);
+            data.y = Some(y);
+            outcome("fire", &fleet, fire(data).await)
+        }
+        Commands::Salvo { fleet, target, at } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            data.targetfleet = Some(target);
+            data.salvo = Some(at);
+            outcome("salvo", &fleet, salvo(data).await)
+        }
+        Commands::Report { fleet, at } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            let status = game_status(&fleet.game)
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            // A salvo is answered as a whole, at the positions the chain registered
+            if status.mode == GameMode::Salvo {
+                let message = salvo_report(data).await;
+                if message == "OK" {
+                    let hits = hits_on(&fleet.game, &fleet.fleet)
+                        .await
+                        .unwrap_or_else(|err| fail(cli.json, &err));
+                    for pos in hits {
+                        store.remove_cell(&fleet.game, &fleet.fleet, pos);
+                    }
+                    save(&store, cli.json);
+                }
+                outcome("report", &fleet, message)
+            } else {
+                let at = match at {
+                    Some(at) => at,
+                    None => match status.last_shot_pos {
+                        Some(pos) => position_label(pos),
+                        None => fail(cli.json, "No shot to report in this game"),
+                    },
+                };
+                let pos = parse_position(&at).unwrap_or_else(|err| fail(cli.json, &err));
+                let hit = store
+                    .get(&fleet.game, &fleet.fleet)
+                    .is_some_and(|s| s.board.contains(&pos));
+                let (x, y) = split_position(&at, cli.json);
+                data.rx = Some(x);
+                data.ry = Some(y);
+                data.report = Some(if hit { "Hit" } else { "Miss" }.to_string());
+                let message = report(data).await;
+                if message == "OK" {
+                    store.remove_cell(&fleet.game, &fleet.fleet, pos);
+                    save(&store, cli.json);
+                }
+                outcome("report", &fleet, message)
+            }
+        }
+        Commands::Scan { fleet, target, at } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            let (x, y) = split_position(&at, cli.json);
+            data.targetfleet = Some(target);
+            data.x = Some(x);
+            data.y = Some(y);
+            outcome("scan", &fleet, scan(data).await)
+        }
+        Commands::AnswerScan { fleet } => {
+            let data = stored_form(&store, &fleet, cli.json);
+            outcome("answer-scan", &fleet, answer_scan(data).await)
+        }
+        Commands::Move {
+            fleet,
+            at,
+            direction,
+        } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            data.ship = Some(at);
+            data.direction = Some(direction);
+            let (_, _, board, _, ship_move) =
+                unmarshal_move(&data).unwrap_or_else(|err| fail(cli.json, &err));
+            let message = move_ship(data).await;
+            if message == "OK" {
+                // Keep the moved board, and the move for the reveal
+                let next_board = reports_on(&fleet.game, &fleet.fleet)
+                    .await
+                    .and_then(|(hits, misses)| moved_board(&board, &hits, &misses, &ship_move))
+                    .unwrap_or_else(|err| fail(cli.json, &err));
+                store.move_ship(&fleet.game, &fleet.fleet, ship_move, next_board);
+                save(&store, cli.json);
+            }
+            outcome("move", &fleet, message)
+        }
+        Commands::Wave { fleet } => {
+            let data = stored_form(&store, &fleet, cli.json);
+            outcome("wave", &fleet, wave(data).await)
+        }
+        Commands::Win { fleet } => {
+            let data = stored_form(&store, &fleet, cli.json);
+            outcome("win", &fleet, win(data).await)
+        }
+        Commands::Resign { fleet, reveal } => {
+            let mut data = stored_form(&store, &fleet, cli.json);
+            if reveal {
+                data.show_board = Some("on".to_string());
+            }
+            outcome("resign", &fleet, resign(data).await)
+        }
+        Commands::Reveal { fleet } => {
+            // Reveal the board committed at join rather than what is left of it
+            let data = match store.get(&fleet.game, &fleet.fleet) {
+                Some(secrets) if !secrets.joined.is_empty() => {
+                    FormData::for_fleet(&fleet.game, &fleet.fleet, &secrets.joined, &secrets.random)
+                }
+                _ => stored_form(&store, &fleet, cli.json),
+            };
+            outcome("reveal", &fleet, reveal(data).await)
+        }
+        Commands::Status { game, fleet } => {
+            let status = game_status(&game)
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            let remaining = fleet
+                .as_ref()
+                .and_then(|fleet| store.get(&game, fleet))
+                .map(|s| s.board.len());
+            print_status(&status, remaining, cli.json);
+            return;
+        }
+        Commands::Export { game, out } => {
+            let archive = export_game(&game)
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            let text = serde_json::to_string_pretty(&archive)
+                .unwrap_or_else(|err| fail(cli.json, &err.to_string()));
+            if let Err(err) = std::fs::write(&out, text) {
+                fail(
+                    cli.json,
+                    &format!("Failed to write {}: {}", out.display(), err),
+                );
+            }
+            let message = format!(
+                "Exported {} transactions of game {} to {}",
+                archive.transactions.len(),
+                game,
+                out.display()
+            );
+            if cli.json {
+                println!("{}", serde_json::json!({ "ok": true, "message": message }));
+            } else {
+                println!("{}", message);
+            }
+            return;
+        }
+        Commands::Summary { game, out, publish } => {
+            let archive = export_game(&game)
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            let summary =
+                prove_summary(&archive).unwrap_or_else(|err| fail(cli.json, &err.to_string()));
+            let text = serde_json::to_string(&summary)
+                .unwrap_or_else(|err| fail(cli.json, &err.to_string()));
+            if let Err(err) = std::fs::write(&out, text) {
+                fail(
+                    cli.json,
+                    &format!("Failed to write {}: {}", out.display(), err),
+                );
+            }
+            let mut message = format!(
+                "Summary of {} transactions of game {} written to {}",
+                archive.transactions.len(),
+                game,
+                out.display()
+            );
+            if publish {
+                let answer = publish_summary(&summary).await;
+                if answer != "OK" {
+                    fail(
+                        cli.json,
+                        &format!("{}; publishing failed: {}", message, answer),
+                    );
+                }
+                message.push_str(" and published");
+            }
+            if cli.json {
+                println!("{}", serde_json::json!({ "ok": true, "message": message }));
+            } else {
+                println!("{}", message);
+            }
+            return;
+        }
+        Commands::Bot {
+            fleet,
+            interval,
+            salvo,
+            team,
+        } => {
+            let options = BotOptions {
+                gameid: fleet.game.clone(),
+                fleetid: fleet.fleet.clone(),
+                interval: std::time::Duration::from_secs(interval),
+                salvo,
+                team,
+            };
+            let message = match run_bot(&options).await {
+                Ok(message) => message,
+                Err(err) => fail(cli.json, &err),
+            };
+            if cli.json {
+                println!("{}", serde_json::json!({ "ok": true, "message": message }));
+            } else {
+                println!("{}", message);
+            }
+            return;
+        }
+        Commands::Leaderboard => {
+            let entries = leaderboard()
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            for entry in &entries {
+                if cli.json {
+                    println!("{}", serde_json::json!(entry));
+                } else {
+                    println!(
+                        "{:>5}  {}  ({} games, {} wins)",
+                        entry.rating, entry.account, entry.games, entry.wins
+                    );
+                }
+            }
+            if entries.is_empty() && !cli.json {
+                println!("No rated game yet");
+            }
+            return;
+        }
+        Commands::Account { new: true } => {
+            let key = nanoid!(32);
+            if cli.json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "account_key": key, "account": account_id(&key) })
+                );
+            } else {
+                println!("Account key:  {}", key);
+                println!("Account:      {}", account_id(&key));
+                println!("Keep the key secret and set it with --account-key or SEGCOM_ACCOUNT_KEY");
+            }
+            return;
+        }
+        Commands::Account { new: false } => {
+            let status = account_status()
+                .await
+                .unwrap_or_else(|err| fail(cli.json, &err));
+            if cli.json {
+                println!("{}", serde_json::json!(status));
+            } else {
+                println!("Account:      {}", status.account);
+                println!("Balance:      {} tokens", status.balance);
+                println!("In escrow:    {} tokens", status.escrow);
+            }
+            return;
+        }
+        Commands::Faucet => {
+            print_message(cli.json, faucet().await);
+            return;
+        }
+        Commands::Refund { game } => {
+            print_message(cli.json, refund(&game).await);
+            return;
+        }
+        Commands::Tournament { action } => {
+            match action {
+                TournamentAction::Create {
+                    tournament,
+                    round_robin,
+                } => {
+                    let format = if round_robin {
+                        TournamentFormat::RoundRobin
+                    } else {
+                        TournamentFormat::Bracket
+                    };
+                    print_message(cli.json, create_tournament(&tournament, format).await);
+                }
+                TournamentAction::Register { tournament, fleet } => {
+                    print_message(cli.json, register_fleet(&tournament, &fleet).await);
+                }
+                TournamentAction::Start { tournament } => {
+                    print_message(cli.json, start_tournament(&tournament).await);
+                }
+                TournamentAction::Forfeit { tournament, game } => {
+                    print_message(cli.json, forfeit(&tournament, &game).await);
+                }
+                TournamentAction::Status { tournament } => {
+                    let status = tournament_status(&tournament)
+                        .await
+                        .unwrap_or_else(|err| fail(cli.json, &err));
+                    print_tournament(&status, cli.json);
+                }
+            }
+            return;
+        }
+        Commands::Pending { game } => {
+            let pending = pending_receipts(game.as_deref());
+            for receipt in &pending {
+                if cli.json {
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "key": receipt.key,
+                            "gameid": receipt.gameid,
+                            "fleet": receipt.fleet,
+                            "command": receipt.data.cmd,
+                            "created": receipt.created,
+                        })
+                    );
+                } else {
+                    println!(
+                        "{}  {:?} by {} in game {}",
+                        receipt.key, receipt.data.cmd, receipt.fleet, receipt.gameid
+                    );
+                }
+            }
+            if pending.is_empty() && !cli.json {
+                println!("No pending receipt");
+            }
+            return;
+        }
+        Commands::Resubmit { key } => {
+            let Some(pending) = pending_receipt(&key) else {
+                fail(cli.json, &format!("No pending receipt {}", key));
+            };
+            let fleet = FleetArgs {
+                game: pending.gameid.clone(),
+                fleet: pending.fleet.clone(),
+            };
+            let hits = pending.reported_hits();
+            let message = resubmit(pending).await;
+            if message == "OK" && !hits.is_empty() {
+                for pos in hits {
+                    store.remove_cell(&fleet.game, &fleet.fleet, pos);
+                }
+                save(&store, cli.json);
+            }
+            outcome("resubmit", &fleet, message)
+        }
+        Commands::Discard { key } => {
+            discard_receipt(&key);
+            return;
+        }
+        Commands::Watch { game } => {
+            if let Err(err) = watch(game.as_deref(), cli.json).await {
+                fail(cli.json, &err);
+            }
+            return;
+        }
+    };
+
+    if cli.json {
+        println!("{}", serde_json::to_string(&outcome).unwrap());
+    } else if outcome.ok {
+        println!("{} OK", outcome.command);
+    } else {
+        println!("{} failed: {}", outcome.command, outcome.message);
+    }
+    if !outcome.ok {
+        std::process::exit(1);
+    }
+}
+
+// Print the answer of the chain to a request that involves no fleet
+fn print_message(json: bool, message: String) {
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "ok": message == "OK", "message": message })
+        );
+    } else {
+        println!("{}", message);
+    }
+    if message != "OK" {
+        std::process::exit(1);
+    }
+}
+
+fn fail(json: bool, message: &str) -> ! {
+    if json {
+        println!("{}", serde_json::json!({ "ok": false, "message": message }));
+    } else {
+        eprintln!("{}", message);
+    }
+    std::process::exit(1);
+}
+
+fn save(store: &BoardStore, json: bool) {
+    if let Err(err) = store.save() {
+        fail(json, &err);
+    }
+}
+
+fn outcome(command: &'static str, fleet: &FleetArgs, message: String) -> Outcome {
+    Outcome {
+        command,
+        gameid: fleet.game.clone(),
+        fleet: fleet.fleet.clone(),
+        ok: message == "OK",
+        message,
+    }
+}
+
+// Build the form for an action of a fleet that already joined from this machine
+fn stored_form(store: &BoardStore, fleet: &FleetArgs, json: bool) -> FormData {
+    match store.get(&fleet.game, &fleet.fleet) {
+        Some(secrets) => {
+            FormData::for_fleet(&fleet.game, &fleet.fleet, &secrets.board, &secrets.random)
+        }
+        None => fail(
+            json,
+            &format!(
+                "No stored board for fleet {} in game {}; join first",
+                fleet.fleet, fleet.game
+            ),
+        ),
+    }
+}
+
+fn join_pending(fleet: &FleetArgs) -> bool {
+    pending_receipts(Some(&fleet.game))
+        .iter()
+        .any(|p| p.fleet == fleet.fleet && p.data.cmd == Command::Join)
+}
+
+fn split_cells(board: &str) -> Result<Vec<u8>, String> {
+    board
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            s.trim()
+                .parse::<u8>()
+                .map_err(|_| "Invalid number in Board Placement".to_string())
+        })
+        .collect()
+}
+
+// Split a position such as "B3" into the X and Y strings expected by the form
+fn split_position(at: &str, json: bool) -> (String, String) {
+    let pos = parse_position(at).unwrap_or_else(|err| fail(json, &err));
+    let label = position_label(pos);
+    let (x, y) = label.split_at(1);
+    (x.to_string(), y.to_string())
+}
+
+fn print_status(status: &fleetcore::GameStatus, remaining: Option<usize>, json: bool) {
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "status": status, "remaining_cells": remaining })
+        );
+        return;
+    }
+    println!("Game:         {}", status.gameid);
+    println!("Players:      {}", status.players.join(", "));
+    if let Some(tournament) = &status.tournament {
+        println!(
+            "Tournament:   {} ({})",
+            tournament,
+            status.roster.join(" vs ")
+        );
+    }
+    for (fleet, team) in &status.teams {
+        println!("Team:         {} in {}", fleet, team);
+    }
+    match (&status.next_player, &status.next_report) {
+        (Some(player), _) => println!("Next to play: {}", player),
+        (None, Some(reporter)) => println!("Awaiting report from: {}", reporter),
+        (None, None) => println!("Next to play: -"),
+    }
+    if status.mode == GameMode::Salvo {
+        println!("Mode:         salvo");
+    }
+    if let Some(pos) = status.last_shot_pos {
+        println!("Last shot:    {}", position_label(pos));
+    }
+    if let Some(remaining) = remaining {
+        println!("Cells afloat: {}", remaining);
+    }
+    for resigned in &status.resigned {
+        match &resigned.remaining {
+            Some(remaining) => {
+                let cells: Vec<String> = remaining.iter().map(|&p| position_label(p)).collect();
+                println!(
+                    "Resigned:     {} with {} afloat",
+                    resigned.fleet,
+                    cells.join(", ")
+                );
+            }
+            None => println!("Resigned:     {}", resigned.fleet),
+        }
+    }
+    match (
+        &status.winner,
+        status.winner.as_ref().and_then(|w| status.teams.get(w)),
+    ) {
+        (Some(winner), Some(team)) => println!("Winner:       team {} ({})", team, winner),
+        (Some(winner), None) => println!("Winner:       {}", winner),
+        (None, _) => {}
+    }
+    if status.stake > 0 {
+        let pot: u64 = status.escrow.iter().map(|e| e.amount).sum();
+        let state = if status.settled {
+            "settled"
+        } else {
+            "in escrow"
+        };
+        println!(
+            "Stakes:       {} tokens each, {} {}",
+            status.stake, pot, state
+        );
+    }
+    for scan in &status.scans {
+        match scan.count {
+            Some(count) => println!(
+                "Scan:         {} on {} around {}: {} ship cells",
+                scan.fleet,
+                scan.target,
+                position_label(scan.center),
+                count
+            ),
+            None => println!(
+                "Scan:         {} on {} around {}: awaiting answer",
+                scan.fleet,
+                scan.target,
+                position_label(scan.center)
+            ),
+        }
+    }
+}
+
+fn print_tournament(status: &TournamentStatus, json: bool) {
+    if json {
+        println!("{}", serde_json::json!(status));
+        return;
+    }
+    println!("Tournament:   {}", status.tournament);
+    let format = match status.format {
+        TournamentFormat::Bracket => "bracket",
+        TournamentFormat::RoundRobin => "round robin",
+    };
+    println!("Format:       {}", format);
+    println!("Guests:       {}", status.version);
+    for fleet in &status.fleets {
+        println!(
+            "Fleet:        {} ({} wins)",
+            fleet,
+            status.wins.get(fleet).unwrap_or(&0)
+        );
+    }
+    if status.rounds.is_empty() {
+        println!("Registration open");
+    }
+    for (idx, round) in status.rounds.iter().enumerate() {
+        for pairing in round {
+            let fleets = pairing.fleets.join(" vs ");
+            match (&pairing.gameid, &pairing.winner) {
+                (Some(gameid), Some(winner)) => println!(
+                    "Round {}:      {} in {}, won by {}",
+                    idx + 1,
+                    fleets,
+                    gameid,
+                    winner
+                ),
+                (Some(gameid), None) => {
+                    println!("Round {}:      {} in {}", idx + 1, fleets, gameid)
+                }
+                (None, _) => println!("Round {}:      {} sits out", idx + 1, fleets),
+            }
+        }
+    }
+    if let Some(champion) = &status.champion {
+        println!("Champion:     {}", champion);
+    }
+}
+
+// Print the chain's transaction log, optionally keeping only one game
+async fn watch(game: Option<&str>, json: bool) -> Result<(), String> {
+    follow_logs(|message| {
+        if game.is_some_and(|game| !mentions_game(message, game)) {
+            return true;
+        }
+        if json {
+            println!("{}", serde_json::json!({ "message": message }));
+        } else {
+            println!("{}", message);
+        }
+        true
+    })
+    .await
+}
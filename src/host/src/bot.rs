@@ -0,0 +1,350 @@
+// src/bot.rs
+// Computer player: joins with a random fleet, fires with a hunt/target strategy
+// driven by the results recorded on the chain and answers incoming shots and scans.
+// Every action goes through the same proofs as a human player.
+use fleetcore::{ships_afloat, GameMode, GameStatus, ShotRecord};
+use nanoid::nanoid;
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::fleet::random_fleet;
+use crate::store::{BoardStore, FleetSecrets};
+use crate::{
+    answer_scan, config, fire, game_status, hits_on, join_game, position_label, report, salvo,
+    salvo_report, wave, win, FormData,
+};
+
+pub struct BotOptions {
+    pub gameid: String,
+    pub fleetid: String,
+    pub interval: Duration,
+    // Fire salvos if the bot fires the first shot of the game
+    pub salvo: bool,
+    // Team to join the game in
+    pub team: Option<String>,
+}
+
+// Play until the bot wins or its fleet is sunk and the game is decided
+pub async fn run_bot(options: &BotOptions) -> Result<String, String> {
+    let gameid = options.gameid.as_str();
+    let fleetid = options.fleetid.as_str();
+    let mut store = BoardStore::open(&config::get().store)?;
+    let mut rng = rand::rng();
+
+    let secrets = match store.get(gameid, fleetid) {
+        Some(secrets) => secrets.clone(),
+        None => {
+            let secrets = FleetSecrets::new(random_fleet(&mut rng), nanoid!(12));
+            store.insert(gameid, fleetid, secrets.clone());
+            store.save()?;
+            secrets
+        }
+    };
+
+    let joined = match game_status(gameid).await {
+        Ok(status) => status.players.iter().any(|p| p == fleetid),
+        Err(_) => false,
+    };
+    if !joined {
+        let data = FormData {
+            team: options.team.clone(),
+            ..FormData::for_fleet(gameid, fleetid, &secrets.board, &secrets.random)
+        };
+        let response = join_game(data).await;
+        if response != "OK" {
+            return Err(format!("Failed to join: {}", response));
+        }
+        println!("bot {}: joined game {}", fleetid, gameid);
+    }
+
+    loop {
+        tokio::time::sleep(options.interval).await;
+        let status = match game_status(gameid).await {
+            Ok(status) => status,
+            Err(err) => {
+                eprintln!("bot {}: {}", fleetid, err);
+                continue;
+            }
+        };
+        let secrets = store
+            .get(gameid, fleetid)
+            .cloned()
+            .ok_or_else(|| "Board store lost the bot's fleet".to_string())?;
+        let data = FormData::for_fleet(gameid, fleetid, &secrets.board, &secrets.random);
+
+        let salvo_game = match status.mode {
+            GameMode::Salvo => true,
+            GameMode::Classic => options.salvo && status.shots.is_empty(),
+        };
+
+        let scanned = status
+            .scans
+            .iter()
+            .any(|s| s.target == fleetid && s.count.is_none());
+        if status.next_report.as_deref() == Some(fleetid) && scanned {
+            let response = answer_scan(data).await;
+            if response == "OK" {
+                println!("bot {}: answered a scan", fleetid);
+            } else {
+                eprintln!("bot {}: scan answer failed: {}", fleetid, response);
+            }
+            continue;
+        }
+        if status.next_report.as_deref() == Some(fleetid) && status.mode == GameMode::Salvo {
+            let response = salvo_report(data).await;
+            if response == "OK" {
+                // The guest decided which shots hit; the chain recorded them
+                match hits_on(gameid, fleetid).await {
+                    Ok(hits) => hits
+                        .into_iter()
+                        .for_each(|pos| store.remove_cell(gameid, fleetid, pos)),
+                    Err(err) => eprintln!("bot {}: {}", fleetid, err),
+                }
+                store.save()?;
+                println!("bot {}: reported the salvo", fleetid);
+            } else {
+                eprintln!("bot {}: salvo report failed: {}", fleetid, response);
+            }
+            continue;
+        }
+        if status.next_report.as_deref() == Some(fleetid) {
+            let Some(pos) = status.last_shot_pos else {
+                continue;
+            };
+            let label = position_label(pos);
+            let (x, y) = label.split_at(1);
+            let hit = secrets.board.contains(&pos);
+            let data = FormData {
+                rx: Some(x.to_string()),
+                ry: Some(y.to_string()),
+                report: Some(hit_label(hit).to_string()),
+                ..data
+            };
+            let response = report(data).await;
+            if response == "OK" {
+                store.remove_cell(gameid, fleetid, pos);
+                store.save()?;
+                println!("bot {}: reported {} at {}", fleetid, hit_label(hit), label);
+            } else {
+                eprintln!("bot {}: report failed: {}", fleetid, response);
+            }
+            continue;
+        }
+
+        let afloat = fleets_afloat(&status);
+        let decided = afloat
+            .iter()
+            .all(|fleet| fleet == &afloat[0] || status.teammates(fleet, &afloat[0]));
+        if secrets.board.is_empty() && decided {
+            return Ok(format!("Fleet {} was sunk", fleetid));
+        }
+        // The turn of a teammate is the bot's as well
+        let on_turn = status
+            .next_player
+            .as_deref()
+            .is_some_and(|player| player == fleetid || status.teammates(player, fleetid));
+        if !on_turn {
+            continue;
+        }
+
+        if secrets.board.is_empty() {
+            // A sunk fleet leaves the turns of its team to the teammates still afloat
+            let teammate_afloat = afloat.iter().any(|fleet| status.teammates(fleet, fleetid));
+            if !teammate_afloat && status.next_player.as_deref() == Some(fleetid) {
+                let response = wave(data).await;
+                println!("bot {}: waved ({})", fleetid, response);
+            }
+            continue;
+        }
+        if afloat
+            .iter()
+            .all(|fleet| fleet == fleetid || status.teammates(fleet, fleetid))
+        {
+            let response = win(data).await;
+            if response == "OK" {
+                return Ok(format!("Fleet {} claimed victory", fleetid));
+            }
+            eprintln!("bot {}: win claim failed: {}", fleetid, response);
+            continue;
+        }
+
+        if salvo_game {
+            let joined = if secrets.joined.is_empty() {
+                &secrets.board
+            } else {
+                &secrets.joined
+            };
+            let count = ships_afloat(joined, &secrets.board);
+            match choose_salvo(&status, fleetid, count, &mut rng) {
+                Some((target, positions)) => {
+                    let labels: Vec<String> =
+                        positions.iter().map(|&pos| position_label(pos)).collect();
+                    let data = FormData {
+                        targetfleet: Some(target.clone()),
+                        salvo: Some(labels.join(",")),
+                        ..data
+                    };
+                    let response = salvo(data).await;
+                    println!(
+                        "bot {}: fired a salvo at {} on {} ({})",
+                        fleetid,
+                        labels.join(", "),
+                        target,
+                        response
+                    );
+                }
+                None => {
+                    let response = wave(data).await;
+                    println!(
+                        "bot {}: nothing left to fire at, waved ({})",
+                        fleetid, response
+                    );
+                }
+            }
+            continue;
+        }
+
+        match choose_shot(&status, fleetid, &mut rng) {
+            Some((target, pos)) => {
+                let label = position_label(pos);
+                let (x, y) = label.split_at(1);
+                let data = FormData {
+                    targetfleet: Some(target.clone()),
+                    x: Some(x.to_string()),
+                    y: Some(y.to_string()),
+                    ..data
+                };
+                let response = fire(data).await;
+                println!(
+                    "bot {}: fired at {} on {} ({})",
+                    fleetid, label, target, response
+                );
+            }
+            None => {
+                let response = wave(data).await;
+                println!(
+                    "bot {}: nothing left to fire at, waved ({})",
+                    fleetid, response
+                );
+            }
+        }
+    }
+}
+
+fn hit_label(hit: bool) -> &'static str {
+    if hit {
+        "Hit"
+    } else {
+        "Miss"
+    }
+}
+
+// Fleets the chain has not recorded as eliminated
+fn fleets_afloat(status: &GameStatus) -> Vec<String> {
+    status
+        .players
+        .iter()
+        .filter(|fleet| !status.eliminated.contains(fleet))
+        .cloned()
+        .collect()
+}
+
+// Pick the opponent and position to fire at.
+// Target mode: cells next to known hits, preferring those in line with two hits.
+// Hunt mode: a random cell of a checkerboard pattern, which every ship of size 2+ covers.
+fn choose_shot<R: Rng>(status: &GameStatus, fleetid: &str, rng: &mut R) -> Option<(String, u8)> {
+    let opponents: Vec<String> = fleets_afloat(status)
+        .into_iter()
+        .filter(|fleet| fleet != fleetid && !status.teammates(fleet, fleetid))
+        .collect();
+
+    let mut best: Option<(u32, String, u8)> = None;
+    let mut hunt: Vec<(String, u8)> = Vec::new();
+    let mut fallback: Vec<(String, u8)> = Vec::new();
+    for target in &opponents {
+        let fired: HashSet<u8> = status
+            .shots
+            .iter()
+            .filter(|s| &s.target == target)
+            .map(|s| s.pos)
+            .collect();
+        let hits: HashSet<u8> = status
+            .shots
+            .iter()
+            .filter(|s| &s.target == target && s.report.as_deref() == Some("Hit"))
+            .map(|s| s.pos)
+            .collect();
+
+        for &hit in &hits {
+            for (dx, dy) in [(1i8, 0i8), (-1, 0), (0, 1), (0, -1)] {
+                let Some(pos) = offset(hit, dx, dy) else {
+                    continue;
+                };
+                if fired.contains(&pos) {
+                    continue;
+                }
+                let aligned = offset(hit, -dx, -dy).is_some_and(|p| hits.contains(&p));
+                let score = if aligned { 3 } else { 1 } + rng.random_range(0..2);
+                if best.as_ref().is_none_or(|(s, _, _)| score > *s) {
+                    best = Some((score, target.clone(), pos));
+                }
+            }
+        }
+
+        for pos in (0..100u8).filter(|p| !fired.contains(p)) {
+            if (pos % 10 + pos / 10) % 2 == 0 {
+                hunt.push((target.clone(), pos));
+            } else {
+                fallback.push((target.clone(), pos));
+            }
+        }
+    }
+
+    if let Some((_, target, pos)) = best {
+        return Some((target, pos));
+    }
+    hunt.choose(rng).or_else(|| fallback.choose(rng)).cloned()
+}
+
+// Pick `count` positions of a single opponent, one shot at a time as if the previous ones
+// had been fired without a result yet
+fn choose_salvo<R: Rng>(
+    status: &GameStatus,
+    fleetid: &str,
+    count: usize,
+    rng: &mut R,
+) -> Option<(String, Vec<u8>)> {
+    let (target, first) = choose_shot(status, fleetid, rng)?;
+    let mut planned = GameStatus {
+        players: vec![fleetid.to_string(), target.clone()],
+        ..status.clone()
+    };
+    let mut positions = vec![first];
+    while positions.len() < count {
+        planned.shots.push(ShotRecord {
+            fleet: fleetid.to_string(),
+            target: target.clone(),
+            pos: positions[positions.len() - 1],
+            report: None,
+        });
+        match choose_shot(&planned, fleetid, rng) {
+            Some((_, pos)) => positions.push(pos),
+            // Fewer cells left than ships afloat: the guest would refuse a short salvo
+            None => return None,
+        }
+    }
+    Some((target, positions))
+}
+
+// Move a position on the board, or None when it would leave the board
+fn offset(pos: u8, dx: i8, dy: i8) -> Option<u8> {
+    let x = (pos % 10) as i8 + dx;
+    let y = (pos / 10) as i8 + dy;
+    if (0..10).contains(&x) && (0..10).contains(&y) {
+        Some((y * 10 + x) as u8)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,166 @@
+// src/config.rs
+// Runtime configuration for the host: command line flags take precedence over
+// environment variables, which take precedence over the config file.
+use clap::{Args, ValueEnum};
+use risc0_zkvm::ProverOpts;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_CHAIN_URL: &str = "http://chain0:3001";
+const DEFAULT_BIND: &str = "0.0.0.0:3000";
+const DEFAULT_ASSETS: &str = "host/assets";
+const DEFAULT_CHAIN_TIMEOUT: u64 = 30;
+const DEFAULT_STORE: &str = "segcom-store.json";
+const DEFAULT_RECEIPTS: &str = "segcom-receipts";
+const DEFAULT_SUBMIT_RETRIES: u32 = 4;
+const DEFAULT_RETRY_DELAY: u64 = 1;
+
+static CONFIG: OnceLock<HostConfig> = OnceLock::new();
+
+// Kind of receipt requested from the prover
+#[derive(Clone, Copy, Debug, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptKind {
+    #[default]
+    Composite,
+    Succinct,
+    Groth16,
+}
+
+// Options accepted on the command line, through the environment or in the config file
+#[derive(Args, Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HostOptions {
+    /// TOML file with default values for the options below
+    #[arg(long, env = "SEGCOM_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+    /// Base URL of the blockchain emulator
+    #[arg(long, env = "SEGCOM_CHAIN_URL")]
+    pub chain_url: Option<String>,
+    /// Address the web interface listens on
+    #[arg(long, env = "SEGCOM_BIND")]
+    pub bind: Option<SocketAddr>,
+    /// Directory with the page template and the static files of the web interface
+    #[arg(long, env = "SEGCOM_ASSETS")]
+    pub assets: Option<PathBuf>,
+    /// Kind of receipt produced by the prover
+    #[arg(long, env = "SEGCOM_RECEIPT_KIND", value_enum)]
+    pub receipt_kind: Option<ReceiptKind>,
+    /// Seconds to wait for the chain before giving up on a submission
+    #[arg(long, env = "SEGCOM_CHAIN_TIMEOUT")]
+    pub chain_timeout: Option<u64>,
+    /// JSON file holding the boards and salts of the local fleets
+    #[arg(long, env = "SEGCOM_STORE")]
+    pub store: Option<PathBuf>,
+    /// Directory keeping the receipts the chain has not accepted yet
+    #[arg(long, env = "SEGCOM_RECEIPTS")]
+    pub receipts: Option<PathBuf>,
+    /// Extra attempts to submit a receipt when the chain cannot be reached
+    #[arg(long, env = "SEGCOM_SUBMIT_RETRIES")]
+    pub submit_retries: Option<u32>,
+    /// Seconds before the first extra attempt, doubled after every failure
+    #[arg(long, env = "SEGCOM_RETRY_DELAY")]
+    pub retry_delay: Option<u64>,
+    /// Secret key of the ledger account stakes are taken from when joining games
+    #[arg(long, env = "SEGCOM_ACCOUNT_KEY", hide_env_values = true)]
+    pub account_key: Option<String>,
+}
+
+// Resolved configuration used by the rest of the host
+#[derive(Clone, Debug)]
+pub struct HostConfig {
+    pub chain_url: String,
+    pub bind: SocketAddr,
+    pub assets: PathBuf,
+    pub receipt_kind: ReceiptKind,
+    pub chain_timeout: Duration,
+    pub store: PathBuf,
+    pub receipts: PathBuf,
+    pub submit_retries: u32,
+    pub retry_delay: Duration,
+    pub account_key: Option<String>,
+}
+
+impl HostOptions {
+    // Fill every unset option with the value found in the config file, if any
+    pub fn resolve(self) -> Result<HostConfig, String> {
+        let file = match &self.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+                toml::from_str::<HostOptions>(&text)
+                    .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?
+            }
+            None => HostOptions::default(),
+        };
+
+        Ok(HostConfig {
+            chain_url: self
+                .chain_url
+                .or(file.chain_url)
+                .unwrap_or_else(|| DEFAULT_CHAIN_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            bind: self
+                .bind
+                .or(file.bind)
+                .unwrap_or_else(|| DEFAULT_BIND.parse().unwrap()),
+            assets: self
+                .assets
+                .or(file.assets)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_ASSETS)),
+            receipt_kind: self.receipt_kind.or(file.receipt_kind).unwrap_or_default(),
+            chain_timeout: Duration::from_secs(
+                self.chain_timeout
+                    .or(file.chain_timeout)
+                    .unwrap_or(DEFAULT_CHAIN_TIMEOUT),
+            ),
+            store: self
+                .store
+                .or(file.store)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_STORE)),
+            receipts: self
+                .receipts
+                .or(file.receipts)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_RECEIPTS)),
+            submit_retries: self
+                .submit_retries
+                .or(file.submit_retries)
+                .unwrap_or(DEFAULT_SUBMIT_RETRIES),
+            retry_delay: Duration::from_secs(
+                self.retry_delay
+                    .or(file.retry_delay)
+                    .unwrap_or(DEFAULT_RETRY_DELAY),
+            ),
+            account_key: self.account_key.or(file.account_key),
+        })
+    }
+}
+
+impl HostConfig {
+    pub fn prover_opts(&self) -> ProverOpts {
+        match self.receipt_kind {
+            ReceiptKind::Composite => ProverOpts::composite(),
+            ReceiptKind::Succinct => ProverOpts::succinct(),
+            ReceiptKind::Groth16 => ProverOpts::groth16(),
+        }
+    }
+
+    pub fn chain_endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.chain_url, path.trim_start_matches('/'))
+    }
+}
+
+// Install the configuration used by the game actions; only the first call has an effect
+pub fn init(config: HostConfig) {
+    let _ = CONFIG.set(config);
+}
+
+// Configuration installed with `init`, or the defaults when none was installed
+pub fn get() -> &'static HostConfig {
+    CONFIG.get_or_init(|| HostOptions::default().resolve().unwrap())
+}
@@ -0,0 +1,55 @@
+// src/fleet.rs
+// Fleet placement helpers built on the rules shared with the join guest.
+use fleetcore::is_valid_fleet;
+use rand::Rng;
+
+// Ship sizes of a fleet, largest first so the big ships find room easily
+const SHIP_SIZES: [u8; 7] = [5, 4, 3, 2, 2, 1, 1];
+
+// Generate a random board placement accepted by the join guest
+pub fn random_fleet<R: Rng>(rng: &mut R) -> Vec<u8> {
+    loop {
+        if let Some(board) = try_place(rng) {
+            if is_valid_fleet(&board) {
+                return board;
+            }
+        }
+    }
+}
+
+// Place every ship at a random free spot, giving up when a ship finds no room
+fn try_place<R: Rng>(rng: &mut R) -> Option<Vec<u8>> {
+    let mut board: Vec<u8> = Vec::new();
+    for size in SHIP_SIZES {
+        let ship = (0..100).find_map(|_| {
+            let horizontal = rng.random_bool(0.5);
+            let (max_x, max_y) = if horizontal {
+                (10 - size, 9)
+            } else {
+                (9, 10 - size)
+            };
+            let x = rng.random_range(0..=max_x);
+            let y = rng.random_range(0..=max_y);
+            let step = if horizontal { 1 } else { 10 };
+            let ship: Vec<u8> = (0..size).map(|i| y * 10 + x + i * step).collect();
+            // Keep ships apart so the guest cannot read two of them as one
+            if ship.iter().any(|&p| touches(&board, p)) {
+                None
+            } else {
+                Some(ship)
+            }
+        })?;
+        board.extend(ship);
+    }
+    board.sort_unstable();
+    Some(board)
+}
+
+// Whether a cell is occupied or orthogonally adjacent to an occupied cell
+fn touches(board: &[u8], pos: u8) -> bool {
+    board.iter().any(|&p| {
+        let (px, py) = (p % 10, p / 10);
+        let (x, y) = (pos % 10, pos / 10);
+        px.abs_diff(x) + py.abs_diff(y) <= 1
+    })
+}
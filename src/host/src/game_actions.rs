@@ -1,9 +1,113 @@
 // src/game_actions.rs
-use fleetcore::{BaseInputs, Command, FireInputs};
-use methods::{FIRE_ELF, JOIN_ELF, REPORT_ELF, WAVE_ELF, WIN_ELF};
-use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
+use fleetcore::{
+    check_fleet, ships_afloat, BaseInputs, Command, CommunicationData, FireInputs, GameArchive,
+    GameStatus, GameSummary, JoinInputs, MoveInputs, ResignInputs, RevealData, SalvoInputs,
+    SalvoReportInputs, ScanInputs, ScanRequestInputs, Shot, SummaryInputs, SummaryStep,
+};
+use methods::{
+    FIRE_ELF, JOIN_ELF, MOVE_ELF, REPORT_ELF, RESIGN_ELF, SALVO_ELF, SALVO_REPORT_ELF, SCAN_ELF,
+    SCAN_REQUEST_ELF, SUMMARY_ELF, WAVE_ELF, WIN_ELF,
+};
+use risc0_zkvm::{default_executor, default_prover, ExecutorEnv, ExitCode, ProverOpts, Receipt};
+use serde::Serialize;
+use std::fmt;
+
+use crate::receipts::{receipt_key, PendingReceipt, ReceiptCache};
+use crate::store::BoardStore;
+use crate::{
+    config, game_status, hits_on, position_label, post_to_chain, reports_on, send_receipt,
+    unmarshal_data, unmarshal_fire, unmarshal_move, unmarshal_report, unmarshal_salvo, FormData,
+};
+
+// Reason why a receipt could not be produced
+#[derive(Debug)]
+pub enum ProofError {
+    // The guest refused the inputs because they break a rule of the game
+    Rejected(String),
+    // The zkVM could not execute or prove the guest
+    Failed(String),
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::Rejected(reason) => write!(f, "Rejected by the game rules: {}", reason),
+            ProofError::Failed(err) => write!(f, "Failed to generate receipt: {}", err),
+        }
+    }
+}
+
+// Reuse the receipt of a previous attempt with the same inputs if the chain did not accept it.
+// Otherwise run the guest in the executor first, so that a rule violation is reported with
+// the guest's own message in seconds, and only then spend the time on a full proof.
+fn prove<T: Serialize>(
+    cmd: Command,
+    gameid: &str,
+    fleet: &str,
+    input: &T,
+    elf: &[u8],
+) -> Result<PendingReceipt, ProofError> {
+    let cache = ReceiptCache::open(&config::get().receipts);
+    let key = receipt_key(cmd, elf, input);
+    if let Some(pending) = cache.get(&key) {
+        return Ok(pending);
+    }
+
+    let session = default_executor()
+        .execute(executor_env(input)?, elf)
+        .map_err(guest_error)?;
+    if session.exit_code != ExitCode::Halted(0) {
+        return Err(ProofError::Rejected(format!(
+            "guest exited with {:?}",
+            session.exit_code
+        )));
+    }
+
+    let prove_info = default_prover()
+        .prove_with_opts(executor_env(input)?, elf, &config::get().prover_opts())
+        .map_err(|err| ProofError::Failed(format!("Failed to prove: {}", err)))?;
+    let pending = PendingReceipt::new(
+        key,
+        gameid,
+        fleet,
+        CommunicationData {
+            cmd,
+            receipt: prove_info.receipt,
+        },
+    );
+    // A receipt that cannot be cached can still be submitted
+    if let Err(err) = cache.insert(&pending) {
+        eprintln!("{}", err);
+    }
+    Ok(pending)
+}
+
+// Set up the zkVM execution environment and write the input
+fn executor_env<T: Serialize>(input: &T) -> Result<ExecutorEnv<'static>, ProofError> {
+    ExecutorEnv::builder()
+        .write(input)
+        .map_err(|err| {
+            ProofError::Failed(format!("Failed to write input to executor env: {}", err))
+        })?
+        .build()
+        .map_err(|err| ProofError::Failed(format!("Failed to build executor env: {}", err)))
+}
 
-use crate::{send_receipt, unmarshal_data, unmarshal_fire, unmarshal_report, FormData};
+// Tell a guest panic (a rule violation) apart from an infrastructure failure.
+// A guest panic is reported as "Guest panicked: panicked at <file>:<line>:<col>:\n<message>"
+fn guest_error(err: anyhow::Error) -> ProofError {
+    let text = format!("{:#}", err);
+    match text.split_once("Guest panicked: ") {
+        Some((_, panic)) => {
+            let message = match panic.split_once('\n') {
+                Some((location, message)) if location.starts_with("panicked at") => message,
+                _ => panic,
+            };
+            ProofError::Rejected(message.trim().to_string())
+        }
+        None => ProofError::Failed(format!("Failed to execute: {}", text)),
+    }
+}
 
 pub async fn join_game(idata: FormData) -> String {
     let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
@@ -11,19 +115,30 @@ pub async fn join_game(idata: FormData) -> String {
         Err(err) => return err,
     };
 
+    // Reject placements the join guest would refuse before spending time on the proof
+    if let Err(reason) = check_fleet(&board) {
+        return format!("Invalid fleet positioning: {}", reason);
+    }
+    let team = idata
+        .team
+        .map(|team| team.trim().to_string())
+        .filter(|team| !team.is_empty());
+
     // Call a helper function to generate the receipt
     let receipt = match generate_receipt(
         gameid.clone(),
         fleetid.clone(),
         board.clone(),
         random.clone(),
+        team,
+        config::get().account_key.clone(),
     ) {
         Ok(receipt) => receipt,
-        Err(err) => return format!("Failed to generate receipt: {}", err),
+        Err(err) => return err.to_string(),
     };
 
     // Send the receipt
-    send_receipt(Command::Join, receipt).await
+    send_receipt(receipt).await
 }
 
 // Helper function to generate the receipt
@@ -32,31 +147,21 @@ fn generate_receipt(
     fleetid: String,
     board: Vec<u8>,
     random: String,
-) -> Result<Receipt, String> {
-    // Construct BaseInputs to send to the zkVM guest
-    let input = BaseInputs {
+    team: Option<String>,
+    account_key: Option<String>,
+) -> Result<PendingReceipt, ProofError> {
+    // Construct JoinInputs to send to the zkVM guest
+    let input = JoinInputs {
         gameid,
         fleet: fleetid,
         board,
         random,
+        team,
+        account_key,
     };
 
-    // Set up the zkVM execution environment and write the input
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .map_err(|err| format!("Failed to write input to executor env: {}", err))?
-        .build()
-        .map_err(|err| format!("Failed to build executor env: {}", err))?;
-
-    // Obtain the default prover
-    let prover = default_prover();
-
-    // Produce a receipt by proving the specified ELF binary
-    let prove_info = prover
-        .prove(env, JOIN_ELF)
-        .map_err(|err| format!("Failed to prove: {}", err))?;
-
-    Ok(prove_info.receipt)
+    // Check the inputs in the executor, then prove the specified ELF binary
+    prove(Command::Join, &input.gameid, &input.fleet, &input, JOIN_ELF)
 }
 
 pub async fn fire(idata: FormData) -> String {
@@ -68,6 +173,23 @@ pub async fn fire(idata: FormData) -> String {
     // Convert coordinates to a single position (0-99) for the FireInputs struct
     let pos: u8 = y * 10 + x;
 
+    // The fire guest proves the shot is not in the fleet's shot history, which the chain
+    // only accepts if it matches the shots it registered from this fleet
+    let shots = match game_status(&gameid).await {
+        Ok(status) => shots_of(&status, &fleetid),
+        Err(err) => return err,
+    };
+    if shots
+        .iter()
+        .any(|s| s.target == targetfleet && s.pos == pos)
+    {
+        return format!(
+            "Already fired at {} of fleet {}",
+            position_label(pos),
+            targetfleet
+        );
+    }
+
     // Call a helper function to generate the receipt for firing
     let receipt = match generate_fire_receipt(
         gameid.clone(),
@@ -76,13 +198,50 @@ pub async fn fire(idata: FormData) -> String {
         random.clone(),
         targetfleet.clone(),
         pos,
+        shots,
     ) {
         Ok(receipt) => receipt,
-        Err(err) => return format!("Failed to generate receipt: {}", err),
+        Err(err) => return err.to_string(),
     };
 
     // Send the receipt
-    send_receipt(Command::Fire, receipt).await
+    send_receipt(receipt).await
+}
+
+// Shots a fleet fired so far, in the order the chain registered them
+fn shots_of(status: &GameStatus, fleetid: &str) -> Vec<Shot> {
+    status
+        .shots
+        .iter()
+        .filter(|s| s.fleet == fleetid)
+        .map(|s| Shot {
+            target: s.target.clone(),
+            pos: s.pos,
+        })
+        .collect()
+}
+
+// Board a fleet joined with: the one stored at join, or else the cells it has left plus those
+// reported hit, in the ascending order every board is committed in
+fn joined_board(status: &GameStatus, fleetid: &str, board: &[u8]) -> Vec<u8> {
+    let stored = BoardStore::open(&config::get().store)
+        .ok()
+        .and_then(|store| store.get(&status.gameid, fleetid).map(|s| s.joined.clone()))
+        .filter(|joined| !joined.is_empty());
+    if let Some(joined) = stored {
+        return joined;
+    }
+    let mut joined = board.to_vec();
+    joined.extend(
+        status
+            .shots
+            .iter()
+            .filter(|s| s.target == fleetid && s.report.as_deref() == Some("Hit"))
+            .map(|s| s.pos),
+    );
+    joined.sort_unstable();
+    joined.dedup();
+    joined
 }
 
 // Helper function to generate the receipt for firing
@@ -93,7 +252,8 @@ fn generate_fire_receipt(
     random: String,
     target: String,
     pos: u8,
-) -> Result<Receipt, String> {
+    shots: Vec<Shot>,
+) -> Result<PendingReceipt, ProofError> {
     // Construct FireInputs to send to the zkVM guest
     let input = FireInputs {
         gameid,
@@ -102,24 +262,11 @@ fn generate_fire_receipt(
         random,
         target,
         pos,
+        shots,
     };
 
-    // Set up the zkVM execution environment and write the input
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .map_err(|err| format!("Failed to write input to executor env: {}", err))?
-        .build()
-        .map_err(|err| format!("Failed to build executor env: {}", err))?;
-
-    // Obtain the default prover
-    let prover = default_prover();
-
-    // Produce a receipt by proving the specified ELF binary
-    let prove_info = prover
-        .prove(env, FIRE_ELF)
-        .map_err(|err| format!("Failed to prove: {}", err))?;
-
-    Ok(prove_info.receipt)
+    // Check the inputs in the executor, then prove the specified ELF binary
+    prove(Command::Fire, &input.gameid, &input.fleet, &input, FIRE_ELF)
 }
 
 pub async fn report(idata: FormData) -> String {
@@ -140,11 +287,11 @@ pub async fn report(idata: FormData) -> String {
         pos,
     ) {
         Ok(receipt) => receipt,
-        Err(err) => return format!("Failed to generate receipt: {}", err),
+        Err(err) => return err.to_string(),
     };
 
     // Send the receipt
-    send_receipt(Command::Report, receipt).await
+    send_receipt(receipt).await
 }
 
 // Helper function to generate the receipt for reporting
@@ -154,7 +301,7 @@ fn generate_report_receipt(
     board: Vec<u8>,
     random: String,
     pos: u8,
-) -> Result<Receipt, String> {
+) -> Result<PendingReceipt, ProofError> {
     // Reuse FireInputs
     let input = FireInputs {
         gameid,
@@ -163,24 +310,237 @@ fn generate_report_receipt(
         random,
         target: String::new(), // Not used for report
         pos,
+        shots: Vec::new(), // Not used for report
     };
 
-    // Set up the zkVM execution environment and write the input
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .map_err(|err| format!("Failed to write input to executor env: {}", err))?
-        .build()
-        .map_err(|err| format!("Failed to build executor env: {}", err))?;
+    // Check the inputs in the executor, then prove the specified ELF binary
+    prove(
+        Command::Report,
+        &input.gameid,
+        &input.fleet,
+        &input,
+        REPORT_ELF,
+    )
+}
 
-    // Obtain the default prover
-    let prover = default_prover();
+// Fire one shot per ship still afloat at a single fleet
+pub async fn salvo(idata: FormData) -> String {
+    let (gameid, fleetid, board, random, targetfleet, positions) = match unmarshal_salvo(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+    let status = match game_status(&gameid).await {
+        Ok(status) => status,
+        Err(err) => return err,
+    };
 
-    // Produce a receipt by proving the specified ELF binary
-    let prove_info = prover
-        .prove(env, REPORT_ELF)
-        .map_err(|err| format!("Failed to prove: {}", err))?;
+    // The salvo guest counts the ships afloat on the board committed at join
+    let joined = joined_board(&status, &fleetid, &board);
+    let afloat = ships_afloat(&joined, &board);
+    if positions.len() != afloat {
+        return format!(
+            "A salvo needs {} shots, one per ship afloat, not {}",
+            afloat,
+            positions.len()
+        );
+    }
+    let shots = shots_of(&status, &fleetid);
+    if let Some(pos) = positions.iter().enumerate().find_map(|(idx, &pos)| {
+        let repeated = positions[..idx].contains(&pos)
+            || shots
+                .iter()
+                .any(|s| s.target == targetfleet && s.pos == pos);
+        repeated.then_some(pos)
+    }) {
+        return format!(
+            "Already fired at {} of fleet {}",
+            position_label(pos),
+            targetfleet
+        );
+    }
+
+    let input = SalvoInputs {
+        gameid,
+        fleet: fleetid,
+        joined,
+        board,
+        random,
+        target: targetfleet,
+        positions,
+        shots,
+    };
+    match prove(
+        Command::Salvo,
+        &input.gameid,
+        &input.fleet,
+        &input,
+        SALVO_ELF,
+    ) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
+}
 
-    Ok(prove_info.receipt)
+// Report every shot of the salvo received, as registered by the chain
+pub async fn salvo_report(idata: FormData) -> String {
+    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+    let positions: Vec<u8> = match game_status(&gameid).await {
+        Ok(status) => status
+            .shots
+            .iter()
+            .filter(|s| s.target == fleetid && s.report.is_none())
+            .map(|s| s.pos)
+            .collect(),
+        Err(err) => return err,
+    };
+    if positions.is_empty() {
+        return "No salvo to report in this game".to_string();
+    }
+
+    let input = SalvoReportInputs {
+        gameid,
+        fleet: fleetid,
+        board,
+        random,
+        positions,
+    };
+    match prove(
+        Command::SalvoReport,
+        &input.gameid,
+        &input.fleet,
+        &input,
+        SALVO_REPORT_ELF,
+    ) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
+}
+
+// Ask how many ship cells of the target fleet lie in the 3x3 area around a position.
+// The scan takes the fleet's turn; the target answers with a proof of the count.
+pub async fn scan(idata: FormData) -> String {
+    let (gameid, fleetid, board, random, targetfleet, x, y) = match unmarshal_fire(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+    let status = match game_status(&gameid).await {
+        Ok(status) => status,
+        Err(err) => return err,
+    };
+    let used = status.scans.iter().filter(|s| s.fleet == fleetid).count();
+    if used >= status.scan_limit as usize {
+        return format!(
+            "No scan left: each fleet may scan {} times in this game",
+            status.scan_limit
+        );
+    }
+
+    let input = ScanRequestInputs {
+        gameid,
+        fleet: fleetid,
+        board,
+        random,
+        target: targetfleet,
+        center: y * 10 + x,
+    };
+    match prove(
+        Command::ScanRequest,
+        &input.gameid,
+        &input.fleet,
+        &input,
+        SCAN_REQUEST_ELF,
+    ) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
+}
+
+// Prove the number of ship cells around the center of the scan awaiting an answer
+pub async fn answer_scan(idata: FormData) -> String {
+    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+    let center = match game_status(&gameid).await {
+        Ok(status) => status
+            .scans
+            .iter()
+            .find(|s| s.target == fleetid && s.count.is_none())
+            .map(|s| s.center),
+        Err(err) => return err,
+    };
+    let Some(center) = center else {
+        return "No scan to answer in this game".to_string();
+    };
+
+    let input = ScanInputs {
+        gameid,
+        fleet: fleetid,
+        board,
+        random,
+        center,
+    };
+    match prove(Command::Scan, &input.gameid, &input.fleet, &input, SCAN_ELF) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
+}
+
+// Move an undamaged ship by one cell instead of firing. The guest checks the move against
+// the cells hit and missed so far, which the chain recorded from the reports.
+pub async fn move_ship(idata: FormData) -> String {
+    let (gameid, fleetid, board, random, ship_move) = match unmarshal_move(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+    let (hits, misses) = match reports_on(&gameid, &fleetid).await {
+        Ok(reports) => reports,
+        Err(err) => return err,
+    };
+
+    let input = MoveInputs {
+        gameid,
+        fleet: fleetid,
+        board,
+        random,
+        hits,
+        misses,
+        ship_move,
+    };
+    match prove(Command::Move, &input.gameid, &input.fleet, &input, MOVE_ELF) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
+}
+
+// Leave the game for good: the chain eliminates the fleet and hands its turn on. With
+// `show_board` set, the journal also shows the cells still afloat.
+pub async fn resign(idata: FormData) -> String {
+    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+
+    let input = ResignInputs {
+        gameid,
+        fleet: fleetid,
+        board,
+        random,
+        reveal: idata.show_board.is_some(),
+    };
+    match prove(
+        Command::Resign,
+        &input.gameid,
+        &input.fleet,
+        &input,
+        RESIGN_ELF,
+    ) {
+        Ok(receipt) => send_receipt(receipt).await,
+        Err(err) => err.to_string(),
+    }
 }
 
 pub async fn wave(idata: FormData) -> String {
@@ -191,11 +551,11 @@ pub async fn wave(idata: FormData) -> String {
     // Call a helper function to generate the receipt for wave
     let receipt = match generate_wave_receipt(gameid.clone(), fleetid.clone(), board, random) {
         Ok(receipt) => receipt,
-        Err(err) => return format!("Failed to generate receipt: {}", err),
+        Err(err) => return err.to_string(),
     };
 
     // Send the receipt    // Send the receipt
-    send_receipt(Command::Wave, receipt).await
+    send_receipt(receipt).await
 }
 
 fn generate_wave_receipt(
@@ -203,7 +563,7 @@ fn generate_wave_receipt(
     fleetid: String,
     board: Vec<u8>,
     random: String,
-) -> Result<Receipt, String> {
+) -> Result<PendingReceipt, ProofError> {
     // Construct BaseInputs to send to the zkVM guest
     let input = BaseInputs {
         gameid,
@@ -212,22 +572,8 @@ fn generate_wave_receipt(
         random,
     };
 
-    // Set up the zkVM execution environment and write the input
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .map_err(|err| format!("Failed to write input to executor env: {}", err))?
-        .build()
-        .map_err(|err| format!("Failed to build executor env: {}", err))?;
-
-    // Obtain the default prover
-    let prover = default_prover();
-
-    // Produce a receipt by proving the specified ELF binary
-    let prove_info = prover
-        .prove(env, WAVE_ELF)
-        .map_err(|err| format!("Failed to prove: {}", err))?;
-
-    Ok(prove_info.receipt)
+    // Check the inputs in the executor, then prove the specified ELF binary
+    prove(Command::Wave, &input.gameid, &input.fleet, &input, WAVE_ELF)
 }
 pub async fn win(idata: FormData) -> String {
     let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
@@ -243,11 +589,11 @@ pub async fn win(idata: FormData) -> String {
         random.clone(),
     ) {
         Ok(receipt) => receipt,
-        Err(err) => return format!("Failed to generate receipt: {}", err),
+        Err(err) => return err.to_string(),
     };
 
     // Send the receipt
-    send_receipt(Command::Win, receipt).await
+    send_receipt(receipt).await
 }
 
 // Helper function to generate the receipt for win
@@ -256,7 +602,7 @@ fn generate_win_receipt(
     fleetid: String,
     board: Vec<u8>,
     random: String,
-) -> Result<Receipt, String> {
+) -> Result<PendingReceipt, ProofError> {
     // Construct BaseInputs to send to the zkVM guest
     let input = BaseInputs {
         gameid,
@@ -265,20 +611,103 @@ fn generate_win_receipt(
         random,
     };
 
-    // Set up the zkVM execution environment and write the input
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .map_err(|err| format!("Failed to write input to executor env: {}", err))?
-        .build()
-        .map_err(|err| format!("Failed to build executor env: {}", err))?;
+    // Check the inputs in the executor, then prove the specified ELF binary
+    prove(Command::Win, &input.gameid, &input.fleet, &input, WIN_ELF)
+}
 
-    // Obtain the default prover
-    let prover = default_prover();
+// Publish the board and salt the fleet joined with, so the chain can audit its reports.
+// No proof is involved: the secrets themselves are checked against the join commitment.
+pub async fn reveal(idata: FormData) -> String {
+    let (gameid, fleetid, mut board, random) = match unmarshal_data(&idata) {
+        Ok(values) => values,
+        Err(err) => return err,
+    };
+
+    // Reveal the board stored at join, which moved ships can only be followed from with the
+    // stored moves
+    let stored = BoardStore::open(&config::get().store)
+        .ok()
+        .and_then(|store| store.get(&gameid, &fleetid).cloned())
+        .filter(|secrets| !secrets.joined.is_empty());
+    let moves = match stored {
+        Some(secrets) => {
+            board = secrets.joined;
+            secrets.moves
+        }
+        None => {
+            // A board that lost its hit cells no longer opens the join commitment: put them back
+            let status = match game_status(&gameid).await {
+                Ok(status) => status,
+                Err(err) => return err,
+            };
+            if status.shots.iter().any(|s| {
+                s.target == fleetid && s.report.as_deref() == Some("Hit") && !board.contains(&s.pos)
+            }) {
+                board = joined_board(&status, &fleetid, &board);
+            }
+            Vec::new()
+        }
+    };
 
-    // Produce a receipt by proving the specified ELF binary
-    let prove_info = prover
-        .prove(env, WIN_ELF)
-        .map_err(|err| format!("Failed to prove: {}", err))?;
+    post_to_chain(
+        "reveal",
+        &RevealData {
+            gameid,
+            fleet: fleetid,
+            board,
+            random,
+            moves,
+        },
+    )
+    .await
+}
+
+// Prove a single receipt standing for every transaction of an archived game. Each receipt of
+// the archive is an assumption of the summary guest, resolved by the prover, and the result
+// is made succinct so that it stays small however long the game was.
+pub fn prove_summary(archive: &GameArchive) -> Result<GameSummary, ProofError> {
+    let input = SummaryInputs {
+        gameid: archive.gameid.clone(),
+        image_ids: archive.image_ids.clone(),
+        steps: archive
+            .transactions
+            .iter()
+            .map(|data| SummaryStep {
+                cmd: data.cmd,
+                journal: data.receipt.journal.bytes.clone(),
+            })
+            .collect(),
+        first_player: archive.status.roster.first().cloned(),
+    };
+    let env = || {
+        let mut builder = ExecutorEnv::builder();
+        for data in &archive.transactions {
+            builder.add_assumption(data.receipt.clone());
+        }
+        builder
+            .write(&input)
+            .map_err(|err| {
+                ProofError::Failed(format!("Failed to write input to executor env: {}", err))
+            })?
+            .build()
+            .map_err(|err| ProofError::Failed(format!("Failed to build executor env: {}", err)))
+    };
 
-    Ok(prove_info.receipt)
+    // As for the other guests, a rule violation is reported before proving
+    let session = default_executor()
+        .execute(env()?, SUMMARY_ELF)
+        .map_err(guest_error)?;
+    if session.exit_code != ExitCode::Halted(0) {
+        return Err(ProofError::Rejected(format!(
+            "guest exited with {:?}",
+            session.exit_code
+        )));
+    }
+    let prove_info = default_prover()
+        .prove_with_opts(env()?, SUMMARY_ELF, &ProverOpts::succinct())
+        .map_err(|err| ProofError::Failed(format!("Failed to prove: {}", err)))?;
+    Ok(GameSummary {
+        gameid: archive.gameid.clone(),
+        receipt: prove_info.receipt,
+    })
 }
@@ -3,46 +3,401 @@
 #![allow(unused_variables)]
 #![allow(dead_code)]
 
-use percent_encoding;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+pub mod bot;
+pub mod config;
+pub mod fleet;
 mod game_actions;
+pub mod receipts;
+pub mod store;
 
-use fleetcore::{Command, CommunicationData};
-use risc0_zkvm::Receipt;
+use fleetcore::{
+    account_id, AccountStatus, Command, CommunicationData, Direction, FaucetRequest,
+    ForfeitRequest, GameArchive, GameStatus, GameSummary, LeaderboardEntry, MethodsInfo,
+    RegisterRequest, ShipMove, TournamentFormat, TournamentRequest, TournamentStatus,
+};
+use methods::{
+    FIRE_ID, JOIN_ID, METHODS_VERSION, MOVE_ID, REPORT_ID, RESIGN_ID, SALVO_ID, SALVO_REPORT_ID,
+    SCAN_ID, SCAN_REQUEST_ID, WAVE_ID, WIN_ID,
+};
 use risc0_zkvm::{default_prover, ExecutorEnv};
+use risc0_zkvm::{Digest, Receipt};
 use std::error::Error;
 
-pub use game_actions::{fire, join_game, report, wave, win};
+pub use game_actions::{
+    answer_scan, fire, join_game, move_ship, prove_summary, report, resign, reveal, salvo,
+    salvo_report, scan, wave, win, ProofError,
+};
+use receipts::{PendingReceipt, ReceiptCache};
 
-async fn send_receipt(action: Command, receipt: Receipt) -> String {
-    let client = reqwest::Client::new();
-    let res = client
-        .post("http://chain0:3001/chain")
-        .json(&CommunicationData {
-            cmd: action,
-            receipt,
+// Submit a receipt, keeping it in the receipt cache until the chain accepts it
+async fn send_receipt(pending: PendingReceipt) -> String {
+    let answer = post_to_chain("chain", &pending.data).await;
+    if answer == "OK" {
+        ReceiptCache::open(&config::get().receipts).remove(&pending.key);
+        answer
+    } else {
+        format!(
+            "{} (receipt {} kept, it can be resubmitted without proving again)",
+            answer, pending.key
+        )
+    }
+}
+
+// Submit again a receipt the chain did not accept
+pub async fn resubmit(pending: PendingReceipt) -> String {
+    send_receipt(pending).await
+}
+
+pub fn pending_receipt(key: &str) -> Option<PendingReceipt> {
+    ReceiptCache::open(&config::get().receipts).get(key)
+}
+
+// Receipts not yet accepted by the chain, oldest first, optionally of a single game
+pub fn pending_receipts(gameid: Option<&str>) -> Vec<PendingReceipt> {
+    ReceiptCache::open(&config::get().receipts)
+        .list()
+        .into_iter()
+        .filter(|p| gameid.is_none_or(|g| p.gameid == g))
+        .collect()
+}
+
+pub fn discard_receipt(key: &str) {
+    ReceiptCache::open(&config::get().receipts).remove(key);
+}
+
+// Post a transaction to the chain, returning its answer or a description of the failure.
+// Failures that may be transient are retried with exponential backoff.
+async fn post_to_chain<T: Serialize>(path: &str, body: &T) -> String {
+    let config = config::get();
+    let mut delay = config.retry_delay;
+    let mut attempt = 0;
+    loop {
+        match try_post(path, body).await {
+            Ok(text) => return text,
+            Err(PostError::Transient(err)) if attempt < config.submit_retries => {
+                attempt += 1;
+                eprintln!(
+                    "{}; retrying in {}s ({}/{})",
+                    err,
+                    delay.as_secs_f32(),
+                    attempt,
+                    config.submit_retries
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(PostError::Transient(err)) | Err(PostError::Final(err)) => return err,
+        }
+    }
+}
+
+// Why a post to the chain got no answer
+enum PostError {
+    // The chain could not be reached or is temporarily unable to answer
+    Transient(String),
+    // The chain refused the request itself, sending it again would not help
+    Final(String),
+}
+
+async fn try_post<T: Serialize>(path: &str, body: &T) -> Result<String, PostError> {
+    let config = config::get();
+    let client = reqwest::Client::builder()
+        .timeout(config.chain_timeout)
+        .build()
+        .map_err(|err| PostError::Final(format!("Error building HTTP client: {}", err)))?;
+    let response = client
+        .post(config.chain_endpoint(path))
+        .json(body)
+        .send()
+        .await
+        .map_err(|err| {
+            eprintln!("Error sending transaction: {}", err);
+            PostError::Transient("Error sending transaction".to_string())
+        })?;
+
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "Failed to read response".to_string());
+    if !status.is_success() {
+        eprintln!("Error: Server responded with status {}: {}", status, text);
+        let err = format!("Error: Server responded with status {}", status);
+        return Err(
+            if status.is_server_error()
+                || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            {
+                PostError::Transient(err)
+            } else {
+                PostError::Final(err)
+            },
+        );
+    }
+    Ok(text)
+}
+
+// Fetch the public state of a game from the chain
+pub async fn game_status(gameid: &str) -> Result<GameStatus, String> {
+    get_game(gameid, "").await
+}
+
+// Fetch the archive of a game from the chain, to be checked offline with verify-archive
+pub async fn export_game(gameid: &str) -> Result<GameArchive, String> {
+    get_game(gameid, "/export").await
+}
+
+// Positions of a fleet the chain recorded as reported hit, to mirror them in the board store
+pub async fn hits_on(gameid: &str, fleetid: &str) -> Result<Vec<u8>, String> {
+    Ok(reports_on(gameid, fleetid).await?.0)
+}
+
+// Positions of a fleet the chain recorded as reported hit and as reported missed
+pub async fn reports_on(gameid: &str, fleetid: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let (mut hits, mut misses) = (Vec::new(), Vec::new());
+    for shot in game_status(gameid).await?.shots {
+        match shot.report.as_deref() {
+            _ if shot.target != fleetid => {}
+            Some("Hit") => hits.push(shot.pos),
+            Some(_) => misses.push(shot.pos),
+            None => {}
+        }
+    }
+    Ok((hits, misses))
+}
+
+// Publish the summary of a game on the chain, which checks it against the game's transactions
+pub async fn publish_summary(summary: &GameSummary) -> String {
+    let path = format!(
+        "games/{}/summary",
+        percent_encoding::utf8_percent_encode(&summary.gameid, percent_encoding::NON_ALPHANUMERIC)
+    );
+    post_to_chain(&path, summary).await
+}
+
+// Ratings of the fleets that played a rated game, best first
+pub async fn leaderboard() -> Result<Vec<LeaderboardEntry>, String> {
+    get_json("leaderboard", || {
+        "The chain does not keep a leaderboard".to_string()
+    })
+    .await
+}
+
+// Ledger account of the configured account key
+pub fn account() -> Result<String, String> {
+    config::get()
+        .account_key
+        .as_deref()
+        .map(account_id)
+        .ok_or_else(|| {
+            "No account key configured: set --account-key or SEGCOM_ACCOUNT_KEY".to_string()
         })
+}
+
+// Tokens of the configured account on the chain
+pub async fn account_status() -> Result<AccountStatus, String> {
+    let account = account()?;
+    get_json(&format!("accounts/{}", account), || {
+        "The chain does not keep a ledger".to_string()
+    })
+    .await
+}
+
+// Ask the chain's faucet to fund the configured account
+pub async fn faucet() -> String {
+    match account() {
+        Ok(account) => post_to_chain("faucet", &FaucetRequest { account }).await,
+        Err(err) => err,
+    }
+}
+
+// Ask the chain to give back the stakes of a game nobody won in time
+pub async fn refund(gameid: &str) -> String {
+    let path = format!(
+        "games/{}/refund",
+        percent_encoding::utf8_percent_encode(gameid, percent_encoding::NON_ALPHANUMERIC)
+    );
+    post_to_chain(&path, &()).await
+}
+
+// Open a tournament on the chain for fleets to register in
+pub async fn create_tournament(tournament: &str, format: TournamentFormat) -> String {
+    let request = TournamentRequest {
+        tournament: tournament.to_string(),
+        format,
+    };
+    post_to_chain("tournaments", &request).await
+}
+
+// Register a fleet in a tournament that has not started
+pub async fn register_fleet(tournament: &str, fleet: &str) -> String {
+    let request = RegisterRequest {
+        fleet: fleet.to_string(),
+    };
+    post_to_chain(&tournament_path(tournament, "/register"), &request).await
+}
+
+// Close the registration of a tournament, which pairs its first round
+pub async fn start_tournament(tournament: &str) -> String {
+    post_to_chain(&tournament_path(tournament, "/start"), &()).await
+}
+
+// Decide a game of a tournament stalled past the refund timeout by forfeit
+pub async fn forfeit(tournament: &str, gameid: &str) -> String {
+    let request = ForfeitRequest {
+        gameid: gameid.to_string(),
+    };
+    post_to_chain(&tournament_path(tournament, "/forfeit"), &request).await
+}
+
+// Fetch the state of a tournament from the chain
+pub async fn tournament_status(tournament: &str) -> Result<TournamentStatus, String> {
+    get_json(&tournament_path(tournament, ""), || {
+        format!("Tournament {} not found", tournament)
+    })
+    .await
+}
+
+fn tournament_path(tournament: &str, resource: &str) -> String {
+    format!(
+        "tournaments/{}{}",
+        percent_encoding::utf8_percent_encode(tournament, percent_encoding::NON_ALPHANUMERIC),
+        resource
+    )
+}
+
+// Check that the chain accepts receipts of the guests built into this host
+pub async fn check_methods() -> Result<String, String> {
+    let info: MethodsInfo = get_json("methods", || {
+        "The chain does not list its guest versions".to_string()
+    })
+    .await?;
+    let local = [
+        (Command::Join, JOIN_ID),
+        (Command::Fire, FIRE_ID),
+        (Command::Report, REPORT_ID),
+        (Command::Wave, WAVE_ID),
+        (Command::Win, WIN_ID),
+        (Command::Salvo, SALVO_ID),
+        (Command::SalvoReport, SALVO_REPORT_ID),
+        (Command::ScanRequest, SCAN_REQUEST_ID),
+        (Command::Scan, SCAN_ID),
+        (Command::Move, MOVE_ID),
+        (Command::Resign, RESIGN_ID),
+    ];
+    let Some(accepted) = info.versions.iter().find(|v| v.version == METHODS_VERSION) else {
+        let known: Vec<&str> = info.versions.iter().map(|v| v.version.as_str()).collect();
+        return Err(format!(
+            "The chain does not accept guests version {} (accepted: {})",
+            METHODS_VERSION,
+            known.join(", ")
+        ));
+    };
+    let same_ids = local.iter().all(|(cmd, id)| {
+        accepted
+            .image_ids
+            .iter()
+            .any(|method| method.cmd == *cmd && method.id == Digest::from(*id))
+    });
+    if !same_ids {
+        return Err(format!(
+            "The chain has different image IDs for guests version {}; rebuild the guests or bump their version",
+            METHODS_VERSION
+        ));
+    }
+    if info.current == METHODS_VERSION {
+        Ok(format!(
+            "The chain accepts guests version {}",
+            METHODS_VERSION
+        ))
+    } else {
+        Ok(format!(
+            "The chain accepts guests version {}, its current version is {}",
+            METHODS_VERSION, info.current
+        ))
+    }
+}
+
+// GET a resource of a game from the chain
+async fn get_game<T: DeserializeOwned>(gameid: &str, resource: &str) -> Result<T, String> {
+    let path = format!(
+        "games/{}{}",
+        percent_encoding::utf8_percent_encode(gameid, percent_encoding::NON_ALPHANUMERIC),
+        resource
+    );
+    get_json(&path, || format!("Game {} not found", gameid)).await
+}
+
+// GET a JSON resource from the chain, with the message to give if it does not exist
+async fn get_json<T: DeserializeOwned>(
+    path: &str,
+    not_found: impl FnOnce() -> String,
+) -> Result<T, String> {
+    let config = config::get();
+    let client = reqwest::Client::builder()
+        .timeout(config.chain_timeout)
+        .build()
+        .map_err(|err| format!("Error building HTTP client: {}", err))?;
+    let response = client
+        .get(config.chain_endpoint(path))
         .send()
-        .await;
-
-    match res {
-        Ok(response) => {
-            let status = response.status();
-            let text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Failed to read response".to_string());
-            if !status.is_success() {
-                eprintln!("Error: Server responded with status {}: {}", status, text);
-                return format!("Error: Server responded with status {}", status);
+        .await
+        .map_err(|err| format!("Error contacting chain: {}", err))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(not_found());
+    }
+    if !response.status().is_success() {
+        return Err(format!(
+            "Error: Server responded with status {}",
+            response.status()
+        ));
+    }
+    response
+        .json::<T>()
+        .await
+        .map_err(|err| format!("Invalid answer from chain: {}", err))
+}
+
+// Follow the chain's transaction log, handing every message to `on_message`
+// until it returns false or the stream ends
+pub async fn follow_logs<F: FnMut(&str) -> bool>(mut on_message: F) -> Result<(), String> {
+    // The log stream is long lived, so no request timeout applies here
+    let mut response = reqwest::Client::new()
+        .get(config::get().chain_endpoint("logs"))
+        .send()
+        .await
+        .map_err(|err| format!("Error contacting chain: {}", err))?;
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| format!("Log stream interrupted: {}", err))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(message) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            if !on_message(message.trim_start()) {
+                return Ok(());
             }
-            text
-        }
-        Err(err) => {
-            eprintln!("Error sending receipt: {}", err);
-            "Error sending receipt".to_string()
         }
     }
+    Ok(())
+}
+
+// Chain messages name games as "game <id>", possibly followed by punctuation
+pub fn mentions_game(message: &str, game: &str) -> bool {
+    let needle = format!("game {}", game);
+    message.match_indices(&needle).any(|(idx, _)| {
+        !matches!(
+            message[idx + needle.len()..].chars().next(),
+            Some(c) if c.is_alphanumeric() || c == '_' || c == '-'
+        )
+    })
 }
 
 #[derive(Deserialize)]
@@ -50,17 +405,56 @@ pub struct FormData {
     pub button: String,
     pub gameid: Option<String>,
     pub fleetid: Option<String>,
+    // Team declared when joining, if any
+    pub team: Option<String>,
     pub targetfleet: Option<String>,
     pub x: Option<String>,
     pub y: Option<String>,
     pub rx: Option<String>,
     pub ry: Option<String>,
     pub report: Option<String>,
+    // Positions of a salvo, e.g. "B3,C4"
+    pub salvo: Option<String>,
+    // Cell of the ship to move, e.g. "B3", and the direction to move it in
+    pub ship: Option<String>,
+    pub direction: Option<String>,
+    // Set to show the cells still afloat when resigning
+    pub show_board: Option<String>,
     pub board: Option<String>,
     pub shots: Option<String>,
     pub random: Option<String>,
 }
 
+impl FormData {
+    // Form for an action of a fleet whose secrets are known locally
+    pub fn for_fleet(gameid: &str, fleetid: &str, board: &[u8], random: &str) -> FormData {
+        let board = board
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        FormData {
+            button: String::new(),
+            gameid: Some(gameid.to_string()),
+            fleetid: Some(fleetid.to_string()),
+            team: None,
+            targetfleet: None,
+            x: None,
+            y: None,
+            rx: None,
+            ry: None,
+            report: None,
+            salvo: None,
+            ship: None,
+            direction: None,
+            show_board: None,
+            board: Some(board),
+            shots: None,
+            random: Some(random.to_string()),
+        }
+    }
+}
+
 pub fn unmarshal_data(idata: &FormData) -> Result<(String, String, Vec<u8>, String), String> {
     let gameid = idata
         .gameid
@@ -112,6 +506,10 @@ pub fn unmarshal_data(idata: &FormData) -> Result<(String, String, Vec<u8>, Stri
                     }
                 })
         })??;
+    // Boards are committed in ascending order, whatever order the cells were typed in, so
+    // that the joined board can be rebuilt from the cells left and those hit
+    let mut board = board;
+    board.sort_unstable();
 
     Ok((gameid, fleetid, board, random))
 }
@@ -137,7 +535,7 @@ fn get_coordinates(x: &Option<String>, y: &Option<String>) -> Result<(u8, u8), S
         .ok_or_else(|| "You must provide a Y coordinate".to_string())
         .and_then(|id| {
             if let Some(first_char) = id.chars().next() {
-                if ('0'..='9').contains(&first_char) {
+                if first_char.is_ascii_digit() {
                     Ok(first_char as u8 - b'0')
                 } else {
                     Err("Y coordinate must be between 0 and 9".to_string())
@@ -150,9 +548,26 @@ fn get_coordinates(x: &Option<String>, y: &Option<String>) -> Result<(u8, u8), S
     Ok((x, y))
 }
 
-pub fn unmarshal_fire(
-    idata: &FormData,
-) -> Result<(String, String, Vec<u8>, String, String, u8, u8), String> {
+// Parse a position written as column letter and row digit, e.g. "B3", into 0-99
+pub fn parse_position(text: &str) -> Result<u8, String> {
+    let text = text.trim().to_ascii_uppercase();
+    if text.len() != 2 {
+        return Err(format!("Invalid position {}: expected e.g. B3", text));
+    }
+    let (x, y) = text.split_at(1);
+    let (x, y) = get_coordinates(&Some(x.to_string()), &Some(y.to_string()))?;
+    Ok(y * 10 + x)
+}
+
+// Inverse of parse_position
+pub fn position_label(pos: u8) -> String {
+    format!("{}{}", (b'A' + pos % 10) as char, pos / 10)
+}
+
+// (gameid, fleetid, board, random, target fleet or report, x, y)
+type ShotData = (String, String, Vec<u8>, String, String, u8, u8);
+
+pub fn unmarshal_fire(idata: &FormData) -> Result<ShotData, String> {
     let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
     let (x, y) = get_coordinates(&idata.x, &idata.y)?;
     let targetfleet = idata
@@ -163,9 +578,7 @@ pub fn unmarshal_fire(
     Ok((gameid, fleetid, board, random, targetfleet, x, y))
 }
 
-pub fn unmarshal_report(
-    idata: &FormData,
-) -> Result<(String, String, Vec<u8>, String, String, u8, u8), String> {
+pub fn unmarshal_report(idata: &FormData) -> Result<ShotData, String> {
     let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
     let (x, y) = get_coordinates(&idata.rx, &idata.ry)?;
     let report = idata
@@ -182,3 +595,57 @@ pub fn unmarshal_report(
 
     Ok((gameid, fleetid, board, random, report, x, y))
 }
+
+// (gameid, fleetid, board, random, target fleet, positions)
+type SalvoData = (String, String, Vec<u8>, String, String, Vec<u8>);
+
+pub fn unmarshal_salvo(idata: &FormData) -> Result<SalvoData, String> {
+    let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
+    let targetfleet = idata
+        .targetfleet
+        .clone()
+        .ok_or_else(|| "You must provide a Target Fleet ID".to_string())?;
+    let positions = idata
+        .salvo
+        .as_deref()
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| "You must provide the positions of the salvo".to_string())?
+        .split(',')
+        .map(parse_position)
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    Ok((gameid, fleetid, board, random, targetfleet, positions))
+}
+
+// (gameid, fleetid, board, random, move)
+type MoveData = (String, String, Vec<u8>, String, ShipMove);
+
+pub fn unmarshal_move(idata: &FormData) -> Result<MoveData, String> {
+    let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
+    let cell = idata
+        .ship
+        .as_deref()
+        .ok_or_else(|| "You must provide a cell of the ship to move".to_string())
+        .and_then(parse_position)?;
+    let direction = idata
+        .direction
+        .as_deref()
+        .ok_or_else(|| "You must provide the direction to move in".to_string())
+        .and_then(parse_direction)?;
+
+    Ok((gameid, fleetid, board, random, ShipMove { cell, direction }))
+}
+
+// Parse a direction written as up, down, left or right
+pub fn parse_direction(text: &str) -> Result<Direction, String> {
+    match text.trim().to_ascii_lowercase().as_str() {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        "left" => Ok(Direction::Left),
+        "right" => Ok(Direction::Right),
+        _ => Err(format!(
+            "Invalid direction {}: expected up, down, left or right",
+            text
+        )),
+    }
+}
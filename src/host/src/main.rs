@@ -4,19 +4,311 @@
 #![allow(dead_code)]
 
 use axum::{
-    extract::Form,
-    response::Html,
+    extract::{Extension, Form, Path, Query},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, Redirect,
+    },
     routing::{get, post},
-    Router,
+    Json, Router,
 };
+use clap::Parser;
+use fleetcore::{check_fleet, moved_board, Command, GameStatus};
+use minijinja::{context, path_loader, Environment, Value};
 use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
 use tokio::signal;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
+use tower_http::services::ServeDir;
 
-use host::{fire, join_game, report, wave, win, FormData};
+use host::config::{self, HostOptions};
+use host::fleet::random_fleet;
+use host::store::{BoardStore, FleetSecrets};
+use host::{
+    answer_scan, fire, follow_logs, game_status, hits_on, join_game, mentions_game, move_ship,
+    pending_receipts, report, reports_on, resign, reveal, salvo, salvo_report, scan, wave, win,
+    FormData,
+};
 use std::net::SocketAddr;
 
-async fn index() -> Html<String> {
-    render_html(None, None, None, None, None, None)
+// Templates of the web interface, loaded from the assets directory
+type Templates = Arc<Environment<'static>>;
+
+// Serialises the read-modify-write cycles of concurrent requests on the board store file
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+// Values of the last submission, rendered into the page and handed to the board script
+#[derive(Serialize)]
+struct Page {
+    gameid: String,
+    fleetid: String,
+    random: String,
+    board: String,
+    shots: String,
+    response: Option<String>,
+    ok: bool,
+    // Every game this host holds secrets for, to switch between them
+    games: Vec<Session>,
+    // Receipts of the current game the chain has not accepted yet
+    pending: Vec<Pending>,
+}
+
+// A receipt waiting for resubmission, without the receipt itself
+#[derive(Serialize)]
+struct Pending {
+    key: String,
+    fleetid: String,
+    action: String,
+}
+
+// A cached receipt of a fleet, as posted by the resubmit and discard buttons
+#[derive(Deserialize)]
+struct PendingForm {
+    key: String,
+    gameid: String,
+    fleetid: String,
+}
+
+// A fleet of the local player in one game
+#[derive(Deserialize, Serialize)]
+struct Session {
+    gameid: String,
+    fleetid: String,
+}
+
+#[derive(Deserialize)]
+struct PlayQuery {
+    game: Option<String>,
+    fleet: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BoardQuery {
+    board: String,
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    game: Option<String>,
+}
+
+// Result of checking a placement against the fleet rules
+#[derive(Serialize)]
+struct Validation {
+    ok: bool,
+    reason: Option<String>,
+}
+
+// Web interface for playing the game
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    options: HostOptions,
+}
+
+// Handler for the page, showing the stored game selected in the query if any
+async fn index(
+    Extension(templates): Extension<Templates>,
+    Query(query): Query<PlayQuery>,
+) -> Html<String> {
+    let (Some(gameid), Some(fleetid)) = (query.game, query.fleet) else {
+        return render_html(&templates, None, None, None, None, None, None);
+    };
+    render_session(&templates, gameid, fleetid, None)
+}
+
+// Render the page of a stored game, with `response` in place of the store lookup result
+fn render_session(
+    templates: &Environment<'static>,
+    gameid: String,
+    fleetid: String,
+    response: Option<String>,
+) -> Html<String> {
+    let stored =
+        BoardStore::open(&config::get().store).map(|store| store.get(&gameid, &fleetid).cloned());
+    let (board, random, stored_response) = match stored {
+        Ok(Some(secrets)) => (
+            Some(join_cells(&secrets.board)),
+            Some(secrets.random),
+            "OK".to_string(),
+        ),
+        Ok(None) => (
+            None,
+            None,
+            format!("No stored board for fleet {} in game {}", fleetid, gameid),
+        ),
+        Err(err) => (None, None, err),
+    };
+    render_html(
+        templates,
+        Some(gameid),
+        Some(fleetid),
+        random,
+        board,
+        None,
+        Some(response.unwrap_or(stored_response)),
+    )
+}
+
+// Handler forgetting the secrets of a stored game and returning to the empty page
+async fn forget(Form(session): Form<Session>) -> Redirect {
+    if let Err(err) = with_store(|store| {
+        store.remove(&session.gameid, &session.fleetid);
+        Ok(())
+    }) {
+        eprintln!("{}", err);
+    }
+    Redirect::to("/")
+}
+
+// Handler submitting again a receipt the chain did not accept, without proving again
+async fn resubmit(
+    Extension(templates): Extension<Templates>,
+    Form(form): Form<PendingForm>,
+) -> Html<String> {
+    let response = match host::pending_receipt(&form.key) {
+        Some(pending) => {
+            let hits = pending.reported_hits();
+            let response = host::resubmit(pending).await;
+            if response == "OK" && !hits.is_empty() {
+                let stored = with_store(|store| {
+                    for &pos in &hits {
+                        store.remove_cell(&form.gameid, &form.fleetid, pos);
+                    }
+                    Ok(())
+                });
+                if let Err(err) = stored {
+                    eprintln!("Failed to update the board store: {}", err);
+                }
+            }
+            response
+        }
+        None => format!("No pending receipt {}", form.key),
+    };
+    render_session(&templates, form.gameid, form.fleetid, Some(response))
+}
+
+// Handler dropping a cached receipt that should not be submitted any more
+async fn discard(Form(form): Form<PendingForm>) -> Redirect {
+    host::discard_receipt(&form.key);
+    Redirect::to(&format!(
+        "/?game={}&fleet={}",
+        percent_encoding::utf8_percent_encode(&form.gameid, percent_encoding::NON_ALPHANUMERIC),
+        percent_encoding::utf8_percent_encode(&form.fleetid, percent_encoding::NON_ALPHANUMERIC)
+    ))
+}
+
+// Run `f` on the board store, saving it afterwards; the file is re-read every time
+// because the command line client may share it
+fn with_store<T>(f: impl FnOnce(&mut BoardStore) -> Result<T, String>) -> Result<T, String> {
+    let _guard = STORE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut store = BoardStore::open(&config::get().store)?;
+    let result = f(&mut store)?;
+    store.save()?;
+    Ok(result)
+}
+
+// The page only knows the board still afloat; reveal the one stored at join when there is one
+fn with_joined_board(data: FormData) -> FormData {
+    let (Some(gameid), Some(fleetid)) = (&data.gameid, &data.fleetid) else {
+        return data;
+    };
+    match BoardStore::open(&config::get().store) {
+        Ok(store) => match store.get(gameid, fleetid) {
+            Some(secrets) if !secrets.joined.is_empty() => FormData {
+                board: Some(join_cells(&secrets.joined)),
+                ..data
+            },
+            _ => data,
+        },
+        Err(_) => data,
+    }
+}
+
+// The chain answers OK to a fleet joining twice but keeps the first commitment: refuse a
+// join whose secrets differ from those stored for the fleet
+fn join_conflict(secrets: &Option<(String, String, Vec<u8>, String)>) -> Option<String> {
+    let (gameid, fleetid, board, random) = secrets.as_ref()?;
+    let store = BoardStore::open(&config::get().store).ok()?;
+    let stored = store.get(gameid, fleetid)?;
+    (!stored.same_join(board, random)).then(|| {
+        format!(
+            "Fleet {} already joined game {} with other secrets",
+            fleetid, gameid
+        )
+    })
+}
+
+fn join_cells(cells: &[u8]) -> String {
+    cells
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Handler returning a random valid board placement as comma separated cells
+async fn random_board() -> String {
+    join_cells(&random_fleet(&mut rand::rng()))
+}
+
+// Handler checking a placement with the same rules as the join guest
+async fn validate(Query(query): Query<BoardQuery>) -> Json<Validation> {
+    let board: Result<Vec<u8>, String> = query
+        .board
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            s.trim()
+                .parse::<u8>()
+                .map_err(|_| "Invalid number in Board Placement".to_string())
+        })
+        .collect();
+    let result = board.and_then(|board| check_fleet(&board));
+    Json(Validation {
+        ok: result.is_ok(),
+        reason: result.err(),
+    })
+}
+
+// Handler relaying the public state of a game from the chain to the page
+async fn game_state(Path(gameid): Path<String>) -> Result<Json<GameStatus>, (StatusCode, String)> {
+    game_status(&gameid)
+        .await
+        .map(Json)
+        .map_err(|err| (StatusCode::BAD_GATEWAY, err))
+}
+
+// Handler relaying the chain's transactions to the page as server-sent events,
+// optionally keeping only those of one game
+async fn events(
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (tx, rx) = mpsc::unbounded_channel::<Event>();
+    tokio::spawn(async move {
+        let result = follow_logs(|message| {
+            if let Some(game) = &query.game {
+                if !mentions_game(message, game) {
+                    return !tx.is_closed();
+                }
+            }
+            tx.send(Event::default().data(message)).is_ok()
+        })
+        .await;
+        // The browser reconnects on its own once the stream ends
+        if let Err(err) = result {
+            let _ = tx.send(Event::default().event("chain-error").data(err));
+        }
+    });
+
+    Sse::new(UnboundedReceiverStream::new(rx).map(Ok)).keep_alive(KeepAlive::default())
 }
 
 fn process_input_data(input_data: FormData) -> FormData {
@@ -29,25 +321,121 @@ fn process_input_data(input_data: FormData) -> FormData {
     }
 }
 
-async fn submit(Form(input_data): Form<FormData>) -> Html<String> {
+async fn submit(
+    Extension(templates): Extension<Templates>,
+    Form(input_data): Form<FormData>,
+) -> Html<String> {
     let gameid = input_data.gameid.clone();
     let fleetid = input_data.fleetid.clone();
     let data = process_input_data(input_data);
     let random = data.random.clone();
     let board = data.board.clone();
     let shots = data.shots.clone();
-    let response_text = match data.button.as_str() {
-        "Join" => join_game(data).await,
+    // Secrets and reported hit as the guests will see them, to update the board store
+    let secrets = host::unmarshal_data(&data).ok();
+    let reported_hit = match host::unmarshal_report(&data) {
+        Ok((_, _, _, _, report, x, y)) if report == "Hit" => Some(y * 10 + x),
+        _ => None,
+    };
+    let ship_move = host::unmarshal_move(&data)
+        .ok()
+        .map(|(_, _, _, _, ship_move)| ship_move);
+    let button = data.button.clone();
+    let response_text = match button.as_str() {
+        "Join" => match join_conflict(&secrets) {
+            Some(err) => err,
+            None => join_game(data).await,
+        },
         "Fire" => fire(data).await,
         "Report" => report(data).await,
+        "Salvo" => salvo(data).await,
+        "Report salvo" => salvo_report(data).await,
+        "Scan" => scan(data).await,
+        "Answer scan" => answer_scan(data).await,
+        "Move" => move_ship(data).await,
         "Wave" => wave(data).await,
         "Win" => win(data).await,
+        "Resign" => resign(data).await,
+        "Reveal" => reveal(with_joined_board(data)).await,
         _ => "Unknown button pressed".to_string(),
     };
-    render_html(gameid, fleetid, random, board, shots, Some(response_text))
+
+    // Keep the secrets of accepted joins so the game can be resumed from the switcher,
+    // and drop the cells that were reported hit as the report guest does.
+    // A join whose receipt is kept for resubmission keeps its secrets as well.
+    let ok = response_text == "OK";
+    // The salvo report guest decides which shots hit: take them from the chain
+    let salvo_hits = match (&secrets, button.as_str()) {
+        (Some((gameid, fleetid, _, _)), "Report salvo") if ok => {
+            hits_on(gameid, fleetid).await.unwrap_or_else(|err| {
+                eprintln!("Failed to read the hits of the salvo: {}", err);
+                Vec::new()
+            })
+        }
+        _ => Vec::new(),
+    };
+    // The move guest proved the moved board without revealing it: compute the same one
+    let moved = match (&secrets, ship_move) {
+        (Some((gameid, fleetid, board, _)), Some(ship_move)) if ok && button == "Move" => {
+            match reports_on(gameid, fleetid)
+                .await
+                .and_then(|(hits, misses)| moved_board(board, &hits, &misses, &ship_move))
+            {
+                Ok(next_board) => Some((ship_move, next_board)),
+                Err(err) => {
+                    eprintln!("Failed to compute the moved board: {}", err);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let board = match &moved {
+        Some((_, next_board)) => Some(join_cells(next_board)),
+        None => board,
+    };
+    if let Some((gameid, fleetid, board, random)) = secrets {
+        let join_pending = button == "Join"
+            && pending_receipts(Some(&gameid))
+                .iter()
+                .any(|p| p.fleet == fleetid && p.data.cmd == Command::Join);
+        let stored = with_store(|store| {
+            match (button.as_str(), reported_hit) {
+                ("Join", _) if (ok || join_pending) && store.get(&gameid, &fleetid).is_none() => {
+                    store.insert(&gameid, &fleetid, FleetSecrets::new(board, random))
+                }
+                ("Report", Some(pos)) if ok => store.remove_cell(&gameid, &fleetid, pos),
+                ("Report salvo", _) => {
+                    for &pos in &salvo_hits {
+                        store.remove_cell(&gameid, &fleetid, pos);
+                    }
+                }
+                ("Move", _) => {
+                    if let Some((ship_move, next_board)) = moved {
+                        store.move_ship(&gameid, &fleetid, ship_move, next_board);
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+        if let Err(err) = stored {
+            eprintln!("Failed to update the board store: {}", err);
+        }
+    }
+    render_html(
+        &templates,
+        gameid,
+        fleetid,
+        random,
+        board,
+        shots,
+        Some(response_text),
+    )
 }
 
 fn render_html(
+    templates: &Environment<'static>,
     gameid: Option<String>,
     fleetid: Option<String>,
     random: Option<String>,
@@ -55,48 +443,88 @@ fn render_html(
     shots: Option<String>,
     response: Option<String>,
 ) -> Html<String> {
-    let fleetid = fleetid.unwrap_or("".to_string());
-    let gameid = gameid.unwrap_or("".to_string());
-    let response_html = if let Some(response) = response {
-        if response == "OK" {
-            if gameid != "" {
-                format!(
-                    "Playing Game: <b>{}</b> with fleet's ID: <b>{}</b> ",
-                    gameid, fleetid
-                )
-            } else {
-                "Not in game".to_string()
-            }
-        } else {
-            format!("<p style='color:red'>{}</p>", response)
-        }
-    } else {
-        "".to_string()
+    let games = BoardStore::open(&config::get().store)
+        .map(|store| {
+            store
+                .fleets()
+                .map(|(gameid, fleetid)| Session {
+                    gameid: gameid.to_string(),
+                    fleetid: fleetid.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            Vec::new()
+        });
+    let pending = match &gameid {
+        Some(gameid) => pending_receipts(Some(gameid))
+            .into_iter()
+            .map(|p| Pending {
+                key: p.key,
+                fleetid: p.fleet,
+                action: format!("{:?}", p.data.cmd),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let page = Page {
+        games,
+        pending,
+        gameid: gameid.unwrap_or_default(),
+        fleetid: fleetid.unwrap_or_default(),
+        random: random.unwrap_or_default(),
+        board: board.unwrap_or_default(),
+        shots: shots.unwrap_or_default(),
+        ok: response.as_deref() == Some("OK"),
+        response,
     };
-    let random = random.unwrap_or("".to_string());
-
-    let board = board.unwrap_or("".to_string());
-    let shots = shots.unwrap_or("".to_string());
-
-    let path = "host/src/page.html";
-    let html = std::fs::read_to_string(path).unwrap();
-    let html = html.replace("{response_html}", &response_html);
-    let html = html.replace("{gameid}", &gameid);
-    let html = html.replace("{fleetid}", &fleetid);
-    let html = html.replace("{random}", &random);
-    let html = html.replace("{board}", &board);
-    let html = html.replace("{shots}", &shots);
 
-    Html(html)
+    let html = templates.get_template("index.html").and_then(|template| {
+        template.render(context! { page => &page, ..Value::from_serialize(&page) })
+    });
+    match html {
+        Ok(html) => Html(html),
+        Err(err) => Html(format!("Failed to render page: {:#}", err)),
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    let config = match Cli::parse().options.resolve() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let addr = config.bind;
+    let mut templates = Environment::new();
+    templates.set_loader(path_loader(&config.assets));
+    let static_files = ServeDir::new(config.assets.join("static"));
+    config::init(config);
+
+    // The chain may start after the host, so an incompatibility is only reported
+    tokio::spawn(async {
+        match host::check_methods().await {
+            Ok(message) => println!("{}", message),
+            Err(err) => eprintln!("Warning: {}", err),
+        }
+    });
+
     let app = Router::new()
         .route("/", get(index))
-        .route("/submit", post(submit));
+        .route("/submit", post(submit))
+        .route("/forget", post(forget))
+        .route("/resubmit", post(resubmit))
+        .route("/discard", post(discard))
+        .route("/random-fleet", get(random_board))
+        .route("/validate", get(validate))
+        .route("/api/games/:gameid", get(game_state))
+        .route("/events", get(events))
+        .nest_service("/static", static_files)
+        .layer(Extension(Arc::new(templates)));
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     println!("Listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
 
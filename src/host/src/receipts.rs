@@ -0,0 +1,136 @@
+// src/receipts.rs
+// Local cache of the receipts the chain has not accepted yet, one JSON file per receipt.
+// A proof takes minutes, so a receipt is kept until the chain answers "OK": pressing the
+// same action again reuses it, and it can be resubmitted explicitly.
+// Only receipts are written here; their journals are public anyway.
+use fleetcore::{Command, CommunicationData, ReportJournal, SalvoReportJournal};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A receipt waiting to be accepted by the chain
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PendingReceipt {
+    pub key: String,
+    pub gameid: String,
+    pub fleet: String,
+    // Seconds since the Unix epoch
+    pub created: u64,
+    pub data: CommunicationData,
+}
+
+impl PendingReceipt {
+    pub fn new(key: String, gameid: &str, fleet: &str, data: CommunicationData) -> Self {
+        PendingReceipt {
+            key,
+            gameid: gameid.to_string(),
+            fleet: fleet.to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            data,
+        }
+    }
+
+    // Positions reported hit, for resubmitted reports to update the board store
+    pub fn reported_hits(&self) -> Vec<u8> {
+        let journal = &self.data.receipt.journal;
+        match self.data.cmd {
+            Command::Report => journal
+                .decode::<ReportJournal>()
+                .ok()
+                .filter(|j| j.report == "Hit")
+                .map(|j| vec![j.pos])
+                .unwrap_or_default(),
+            Command::SalvoReport => journal
+                .decode::<SalvoReportJournal>()
+                .map(|j| {
+                    j.positions
+                        .into_iter()
+                        .zip(j.reports)
+                        .filter(|(_, report)| report == "Hit")
+                        .map(|(pos, _)| pos)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+pub struct ReceiptCache {
+    dir: PathBuf,
+}
+
+// Identify the proof of a command by a hash of its guest and inputs, which include game
+// and fleet; rebuilt guests do not pick up receipts the chain would no longer accept
+pub fn receipt_key<T: Serialize>(cmd: Command, elf: &[u8], input: &T) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(elf);
+    hasher.update(serde_json::to_vec(&(cmd, input)).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+impl ReceiptCache {
+    pub fn open(dir: &Path) -> Self {
+        ReceiptCache {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<PendingReceipt> {
+        // Keys come from users when resubmitting: only accept what receipt_key produces
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let text = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn insert(&self, pending: &PendingReceipt) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|err| {
+            format!(
+                "Failed to create receipt cache {}: {}",
+                self.dir.display(),
+                err
+            )
+        })?;
+        let text = serde_json::to_string(pending)
+            .map_err(|err| format!("Failed to encode receipt: {}", err))?;
+        // Write then rename, so that an interrupted write never leaves half a receipt
+        let path = self.path(&pending.key);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .map_err(|err| format!("Failed to write receipt {}: {}", path.display(), err))
+    }
+
+    pub fn remove(&self, key: &str) {
+        if self.get(key).is_some() {
+            let _ = std::fs::remove_file(self.path(key));
+        }
+    }
+
+    // Every cached receipt, oldest first
+    pub fn list(&self) -> Vec<PendingReceipt> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut pending: Vec<PendingReceipt> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let key = name.to_str()?.strip_suffix(".json")?;
+                self.get(key)
+            })
+            .collect();
+        pending.sort_by_key(|p| p.created);
+        pending
+    }
+}
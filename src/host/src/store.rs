@@ -0,0 +1,140 @@
+// src/store.rs
+// Local store for the secrets of each fleet (board placement and random salt).
+// The secrets never leave this machine; only their commitments reach the chain.
+use fleetcore::ShipMove;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Secrets of one fleet in one game
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FleetSecrets {
+    pub board: Vec<u8>,
+    pub random: String,
+    // Board as committed when joining; `board` loses the cells that get hit
+    #[serde(default)]
+    pub joined: Vec<u8>,
+    // Ships moved so far, in order, which the reveal lists for the audit
+    #[serde(default)]
+    pub moves: Vec<ShipMove>,
+}
+
+impl FleetSecrets {
+    // Secrets of a join, the board sorted as the join commits it
+    pub fn new(mut board: Vec<u8>, random: String) -> Self {
+        board.sort_unstable();
+        FleetSecrets {
+            joined: board.clone(),
+            board,
+            random,
+            moves: Vec::new(),
+        }
+    }
+
+    // Whether joining with `board` and `random` commits these secrets again
+    pub fn same_join(&self, board: &[u8], random: &str) -> bool {
+        let joined = if self.joined.is_empty() {
+            &self.board
+        } else {
+            &self.joined
+        };
+        let (mut joined, mut board) = (joined.clone(), board.to_vec());
+        joined.sort_unstable();
+        board.sort_unstable();
+        joined == board && self.random == random
+    }
+}
+
+// File backed map of game to fleet to the fleet secrets
+#[derive(Debug)]
+pub struct BoardStore {
+    path: PathBuf,
+    games: BTreeMap<String, BTreeMap<String, FleetSecrets>>,
+}
+
+impl BoardStore {
+    // Load the store from disk; a missing file yields an empty store
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let games = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|err| format!("Invalid board store {}: {}", path.display(), err))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(format!(
+                    "Failed to read board store {}: {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        Ok(BoardStore {
+            path: path.to_path_buf(),
+            games,
+        })
+    }
+
+    pub fn get(&self, gameid: &str, fleetid: &str) -> Option<&FleetSecrets> {
+        self.games.get(gameid)?.get(fleetid)
+    }
+
+    pub fn insert(&mut self, gameid: &str, fleetid: &str, secrets: FleetSecrets) {
+        self.games
+            .entry(gameid.to_string())
+            .or_default()
+            .insert(fleetid.to_string(), secrets);
+    }
+
+    // Forget the secrets of a fleet, e.g. once its game is over
+    pub fn remove(&mut self, gameid: &str, fleetid: &str) {
+        if let Some(fleets) = self.games.get_mut(gameid) {
+            fleets.remove(fleetid);
+            if fleets.is_empty() {
+                self.games.remove(gameid);
+            }
+        }
+    }
+
+    // (game, fleet) pairs of every stored fleet, ordered by game
+    pub fn fleets(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.games.iter().flat_map(|(gameid, fleets)| {
+            fleets
+                .keys()
+                .map(move |fleetid| (gameid.as_str(), fleetid.as_str()))
+        })
+    }
+
+    // Remove a position that was hit, mirroring the board update done by the report guest
+    pub fn remove_cell(&mut self, gameid: &str, fleetid: &str, pos: u8) {
+        if let Some(secrets) = self
+            .games
+            .get_mut(gameid)
+            .and_then(|fleets| fleets.get_mut(fleetid))
+        {
+            secrets.board.retain(|&p| p != pos);
+        }
+    }
+
+    // Record a proven move and the board it leads to
+    pub fn move_ship(&mut self, gameid: &str, fleetid: &str, ship_move: ShipMove, board: Vec<u8>) {
+        if let Some(secrets) = self
+            .games
+            .get_mut(gameid)
+            .and_then(|fleets| fleets.get_mut(fleetid))
+        {
+            secrets.board = board;
+            secrets.moves.push(ship_move);
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.games)
+            .map_err(|err| format!("Failed to encode board store: {}", err))?;
+        std::fs::write(&self.path, text).map_err(|err| {
+            format!(
+                "Failed to write board store {}: {}",
+                self.path.display(),
+                err
+            )
+        })
+    }
+}
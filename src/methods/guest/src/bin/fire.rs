@@ -1,4 +1,4 @@
-use fleetcore::{FireInputs, FireJournal};
+use fleetcore::{shots_digest, FireInputs, FireJournal, Shot};
 use risc0_zkvm::guest::env;
 use risc0_zkvm::Digest;
 use sha2::{Digest as _, Sha256};
@@ -25,6 +25,28 @@ fn main() {
     if input.fleet == input.target {
         panic!("Cannot fire at own fleet!");
     }
+
+    // Check that the fleet never fired at this position of the target before
+    if input
+        .shots
+        .iter()
+        .any(|shot| shot.target == input.target && shot.pos == input.pos)
+    {
+        panic!(
+            "Already fired at position {} of fleet {}",
+            input.pos, input.target
+        );
+    }
+
+    // Commit to the shot history before and after this shot
+    let shots_before = shots_digest(&input.shots);
+    let mut next_shots = input.shots;
+    next_shots.push(Shot {
+        target: input.target.clone(),
+        pos: input.pos,
+    });
+    let shots_after = shots_digest(&next_shots);
+
     // Hash the board before the shot using (random || board)
     let mut board_preimage = input.random.as_bytes().to_vec();
     board_preimage.extend_from_slice(&input.board);
@@ -39,6 +61,8 @@ fn main() {
         board: board_digest,
         target: input.target,
         pos: input.pos,
+        shots: shots_before,
+        next_shots: shots_after,
     };
 
     // Write public output to the journal
@@ -0,0 +1,41 @@
+use fleetcore::{moved_board, MoveInputs, MoveJournal};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: MoveInputs = env::read();
+
+    // Apply the move, checking the ship is intact, stays on the board, avoids the cells shot
+    // at, overlaps nothing and leaves a fleet that follows the fleet rules
+    let next_board = match moved_board(&input.board, &input.hits, &input.misses, &input.ship_move) {
+        Ok(next_board) => next_board,
+        Err(err) => panic!("Invalid move: {}", err),
+    };
+
+    let mut hits = input.hits;
+    hits.sort_unstable();
+    let mut misses = input.misses;
+    misses.sort_unstable();
+
+    let output = MoveJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        board: board_digest(&input.random, &input.board),
+        next_board: board_digest(&input.random, &next_board),
+        hits,
+        misses,
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
+
+// Hash a board using (random || board)
+fn board_digest(random: &str, board: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(random.as_bytes());
+    hasher.update(board);
+    Digest::from_bytes(hasher.finalize().into())
+}
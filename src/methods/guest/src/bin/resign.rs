@@ -0,0 +1,32 @@
+use fleetcore::{ResignInputs, ResignJournal};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: ResignInputs = env::read();
+
+    // Hash the board using (random || board), the commitment the chain holds
+    let mut hasher = Sha256::new();
+    hasher.update(input.random.as_bytes());
+    hasher.update(&input.board);
+    let board_digest = Digest::from_bytes(hasher.finalize().into());
+
+    // The cells afloat are only shown if the fleet asked to; they open the commitment above
+    let remaining = if input.reveal {
+        Some(input.board)
+    } else {
+        None
+    };
+
+    let output = ResignJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        board: board_digest,
+        remaining,
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
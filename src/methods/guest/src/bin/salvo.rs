@@ -0,0 +1,81 @@
+use fleetcore::{check_fleet, ships_afloat, shots_digest, SalvoInputs, SalvoJournal, Shot};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: SalvoInputs = env::read();
+
+    // The joined board must be a valid fleet, and the current board what is left of it
+    if let Err(err) = check_fleet(&input.joined) {
+        panic!("Invalid joined fleet: {}", err);
+    }
+    if input.board.iter().any(|pos| !input.joined.contains(pos)) {
+        panic!("Current board has cells outside the joined fleet");
+    }
+
+    // Check if target is different from own fleet
+    if input.fleet == input.target {
+        panic!("Cannot fire at own fleet!");
+    }
+
+    // One shot per ship still afloat
+    let afloat = ships_afloat(&input.joined, &input.board);
+    if afloat == 0 {
+        panic!("Cannot fire: fleet is completely sunk!");
+    }
+    if input.positions.len() != afloat {
+        panic!(
+            "A salvo of {} shots was given but {} ships are afloat",
+            input.positions.len(),
+            afloat
+        );
+    }
+
+    // Validate the positions, each new for this target
+    let mut next_shots = input.shots.clone();
+    for &pos in &input.positions {
+        if pos >= 100 {
+            panic!(
+                "Invalid shot position: {}. Must be within the 10x10 board (0-99).",
+                pos
+            );
+        }
+        if next_shots
+            .iter()
+            .any(|shot| shot.target == input.target && shot.pos == pos)
+        {
+            panic!(
+                "Already fired at position {} of fleet {}",
+                pos, input.target
+            );
+        }
+        next_shots.push(Shot {
+            target: input.target.clone(),
+            pos,
+        });
+    }
+
+    let output = SalvoJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        initial: board_digest(&input.random, &input.joined),
+        board: board_digest(&input.random, &input.board),
+        target: input.target,
+        positions: input.positions,
+        shots: shots_digest(&input.shots),
+        next_shots: shots_digest(&next_shots),
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
+
+// Hash a board using (random || board)
+fn board_digest(random: &str, board: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(random.as_bytes());
+    hasher.update(board);
+    Digest::from_bytes(hasher.finalize().into())
+}
@@ -0,0 +1,50 @@
+use fleetcore::{SalvoReportInputs, SalvoReportJournal};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: SalvoReportInputs = env::read();
+
+    // Report every position of the salvo in order, removing the cells hit from the board
+    let mut next_board = input.board.clone();
+    let mut reports = Vec::with_capacity(input.positions.len());
+    for (idx, &pos) in input.positions.iter().enumerate() {
+        if pos >= 100 {
+            panic!(
+                "Invalid shot position: {}. Must be within the 10x10 board (0-99).",
+                pos
+            );
+        }
+        if input.positions[..idx].contains(&pos) {
+            panic!("Position {} appears twice in the salvo", pos);
+        }
+        if next_board.contains(&pos) {
+            next_board.retain(|&p| p != pos);
+            reports.push("Hit".to_string());
+        } else {
+            reports.push("Miss".to_string());
+        }
+    }
+
+    let output = SalvoReportJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        positions: input.positions,
+        reports,
+        board: board_digest(&input.random, &input.board),
+        next_board: board_digest(&input.random, &next_board),
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
+
+// Hash a board using (random || board)
+fn board_digest(random: &str, board: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(random.as_bytes());
+    hasher.update(board);
+    Digest::from_bytes(hasher.finalize().into())
+}
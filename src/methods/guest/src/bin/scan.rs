@@ -0,0 +1,40 @@
+use fleetcore::{scan_area, ScanInputs, ScanJournal};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: ScanInputs = env::read();
+
+    // Validate the center of the area
+    if input.center >= 100 {
+        panic!(
+            "Invalid scan position: {}. Must be within the 10x10 board (0-99).",
+            input.center
+        );
+    }
+
+    // Count the ship cells in the area; which ones they are stays private
+    let count = scan_area(input.center)
+        .iter()
+        .filter(|pos| input.board.contains(pos))
+        .count() as u8;
+
+    // Hash the board using (random || board), the commitment the chain holds
+    let mut hasher = Sha256::new();
+    hasher.update(input.random.as_bytes());
+    hasher.update(&input.board);
+    let board_digest = Digest::from_bytes(hasher.finalize().into());
+
+    let output = ScanJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        board: board_digest,
+        center: input.center,
+        count,
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
@@ -0,0 +1,43 @@
+use fleetcore::{ScanRequestInputs, ScanRequestJournal};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::Digest;
+use sha2::{Digest as _, Sha256};
+
+fn main() {
+    // Read the input
+    let input: ScanRequestInputs = env::read();
+
+    // Validate the center of the area
+    if input.center >= 100 {
+        panic!(
+            "Invalid scan position: {}. Must be within the 10x10 board (0-99).",
+            input.center
+        );
+    }
+
+    // Like firing, scanning takes a fleet that is not sunk
+    if input.board.is_empty() {
+        panic!("Cannot scan: fleet is completely sunk!");
+    }
+
+    if input.fleet == input.target {
+        panic!("Cannot scan own fleet!");
+    }
+
+    // Hash the board using (random || board)
+    let mut hasher = Sha256::new();
+    hasher.update(input.random.as_bytes());
+    hasher.update(&input.board);
+    let board_digest = Digest::from_bytes(hasher.finalize().into());
+
+    let output = ScanRequestJournal {
+        gameid: input.gameid,
+        fleet: input.fleet,
+        board: board_digest,
+        target: input.target,
+        center: input.center,
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
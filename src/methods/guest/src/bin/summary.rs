@@ -0,0 +1,454 @@
+use fleetcore::{
+    shots_digest, teammates, BaseJournal, Command, FireJournal, FleetSummary, GameMode,
+    JoinJournal, MoveJournal, ReportJournal, ResignJournal, SalvoJournal, SalvoReportJournal,
+    ScanJournal, ScanRequestJournal, SummaryInputs, SummaryJournal, FLEET_CELLS,
+};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::serde::from_slice;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+// Replays the accepted transactions of a game with the same turn rules as the blockchain
+// server. Each journal is checked by composition: the prover must hold a receipt of the
+// given guest for it, so the single receipt of this guest stands for all of them.
+fn main() {
+    // Read the input
+    let input: SummaryInputs = env::read();
+    // A game reserved by a tournament starts with the turn of the fleet paired first
+    let mut game = Game {
+        next_player: input.first_player.clone(),
+        ..Game::default()
+    };
+
+    for (idx, step) in input.steps.iter().enumerate() {
+        let id = match input.image_ids.iter().find(|m| m.cmd == step.cmd) {
+            Some(method) => method.id,
+            None => panic!("No image ID given for {:?}", step.cmd),
+        };
+        // Only succeeds if the receipt of this journal was added as an assumption
+        env::verify(id, &step.journal).unwrap();
+
+        let gameid = match step.cmd {
+            Command::Join => game.join(decode(idx, &step.journal)),
+            Command::Fire => game.fire(decode(idx, &step.journal)),
+            Command::Report => game.report(decode(idx, &step.journal)),
+            Command::Wave => game.wave(decode(idx, &step.journal)),
+            Command::Win => game.win(decode(idx, &step.journal)),
+            Command::Salvo => game.salvo(decode(idx, &step.journal)),
+            Command::SalvoReport => game.salvo_report(decode(idx, &step.journal)),
+            Command::ScanRequest => game.scan_request(decode(idx, &step.journal)),
+            Command::Scan => game.scan(decode(idx, &step.journal)),
+            Command::Move => game.move_ship(decode(idx, &step.journal)),
+            Command::Resign => game.resign(decode(idx, &step.journal)),
+        };
+        if gameid != input.gameid {
+            panic!("Transaction {} belongs to game {}", idx + 1, gameid);
+        }
+    }
+
+    let output = SummaryJournal {
+        gameid: input.gameid,
+        image_ids: input.image_ids,
+        transactions: input.steps.len() as u32,
+        fleets: game.fleets,
+        eliminated: game.eliminated,
+        winner: game.winner,
+        first_player: input.first_player,
+    };
+
+    // Write public output to the journal
+    env::commit(&output);
+}
+
+fn decode<T: DeserializeOwned>(idx: usize, journal: &[u8]) -> T {
+    match from_slice(journal) {
+        Ok(journal) => journal,
+        Err(err) => panic!("Invalid journal in transaction {}: {}", idx + 1, err),
+    }
+}
+
+// Public state of the game, as tracked by the blockchain server
+#[derive(Default)]
+struct Game {
+    fleets: Vec<FleetSummary>,
+    // Board each fleet joined with, which a salvo counts the ships afloat against
+    joined: Vec<(String, risc0_zkvm::Digest)>,
+    // Team declared by each fleet that joined with one
+    teams: BTreeMap<String, String>,
+    // Fixed by the first shot of the game
+    mode: Option<GameMode>,
+    // Positions of the last salvo, all answered by a single report
+    pending: Vec<u8>,
+    // Target and center of a scan awaiting its answer
+    pending_scan: Option<(String, u8)>,
+    // (fleet, position) of every cell reported hit or missed, which a move is checked with
+    hit_cells: Vec<(String, u8)>,
+    miss_cells: Vec<(String, u8)>,
+    // Set by the first move, after which salvos are no longer sized correctly
+    moved: bool,
+    next_player: Option<String>,
+    next_report: Option<String>,
+    last_player: Option<String>,
+    last_shot_pos: Option<u8>,
+    eliminated: Vec<String>,
+    // Fleets that left the game and may not act any more
+    resigned: Vec<String>,
+    winner: Option<String>,
+}
+
+impl Game {
+    // Fleet of the game whose current board is `board`
+    fn fleet(&mut self, fleet: &str, board: &risc0_zkvm::Digest) -> &mut FleetSummary {
+        if self.resigned.iter().any(|f| f == fleet) {
+            panic!("Fleet {} acted after resigning", fleet);
+        }
+        let summary = match self.fleets.iter_mut().find(|f| f.fleet == fleet) {
+            Some(summary) => summary,
+            None => panic!("Fleet {} has not joined", fleet),
+        };
+        if &summary.board != board {
+            panic!("Board of fleet {} does not match its state", fleet);
+        }
+        summary
+    }
+
+    fn teammates(&self, fleet: &str, other: &str) -> bool {
+        teammates(&self.teams, fleet, other)
+    }
+
+    // The turn of a fleet is shared by its whole team
+    fn on_turn(&self, fleet: &str) -> bool {
+        match &self.next_player {
+            Some(player) => player == fleet || self.teammates(player, fleet),
+            None => false,
+        }
+    }
+
+    fn join(&mut self, data: JoinJournal) -> String {
+        if self.fleets.iter().any(|f| f.fleet == data.fleet) {
+            panic!("Fleet {} joined twice", data.fleet);
+        }
+        // The first fleet to join plays first, unless the game was reserved with a first player
+        if self.fleets.is_empty() && self.next_player.is_none() {
+            self.next_player = Some(data.fleet.clone());
+        }
+        self.joined.push((data.fleet.clone(), data.board));
+        if let Some(team) = data.team {
+            self.teams.insert(data.fleet.clone(), team);
+        }
+        self.fleets.push(FleetSummary {
+            fleet: data.fleet,
+            board: data.board,
+            shots: shots_digest(&[]),
+            hits: 0,
+        });
+        data.gameid
+    }
+
+    // Every shot of a game must follow the mode of the first one
+    fn shoot(&mut self, mode: GameMode) {
+        if *self.mode.get_or_insert(mode) != mode {
+            panic!("A {:?} shot in a {:?} game", mode, self.mode);
+        }
+    }
+
+    fn fire(&mut self, data: FireJournal) -> String {
+        self.shoot(GameMode::Classic);
+        let fleet = self.fleet(&data.fleet, &data.board);
+        if fleet.shots != data.shots {
+            panic!(
+                "Shot history of fleet {} does not match its state",
+                data.fleet
+            );
+        }
+        fleet.shots = data.next_shots;
+        if !self.on_turn(&data.fleet) {
+            panic!("Fleet {} fired out of turn", data.fleet);
+        }
+        if !self.fleets.iter().any(|f| f.fleet == data.target) {
+            panic!("Target fleet {} has not joined", data.target);
+        }
+        if self.teammates(&data.fleet, &data.target) {
+            panic!("Fleet {} fired at its teammate {}", data.fleet, data.target);
+        }
+        if self.eliminated.contains(&data.target) {
+            panic!(
+                "Fleet {} fired at the eliminated fleet {}",
+                data.fleet, data.target
+            );
+        }
+        self.next_report = Some(data.target);
+        self.next_player = None;
+        self.last_player = Some(data.fleet);
+        self.last_shot_pos = Some(data.pos);
+        data.gameid
+    }
+
+    fn report(&mut self, data: ReportJournal) -> String {
+        if self.pending_scan.is_some() {
+            panic!(
+                "Fleet {} reported a shot instead of answering a scan",
+                data.fleet
+            );
+        }
+        if self.mode != Some(GameMode::Classic) {
+            panic!("Single shot report in a game without single shots");
+        }
+        if self.next_report.as_ref() != Some(&data.fleet) {
+            panic!("Fleet {} reported out of turn", data.fleet);
+        }
+        if self.last_shot_pos != Some(data.pos) {
+            panic!(
+                "Fleet {} reported another position than the shot",
+                data.fleet
+            );
+        }
+        if data.report == "Hit" {
+            self.hit_cells.push((data.fleet.clone(), data.pos));
+        } else {
+            self.miss_cells.push((data.fleet.clone(), data.pos));
+        }
+        let fleet = self.fleet(&data.fleet, &data.board);
+        fleet.board = data.next_board;
+        if data.report == "Hit" {
+            fleet.hits += 1;
+        }
+        let sunk = fleet.hits as usize >= FLEET_CELLS;
+        if sunk && !self.eliminated.contains(&data.fleet) {
+            self.eliminated.push(data.fleet.clone());
+        }
+        self.next_player = Some(data.fleet);
+        self.next_report = None;
+        data.gameid
+    }
+
+    fn salvo(&mut self, data: SalvoJournal) -> String {
+        self.shoot(GameMode::Salvo);
+        if self.moved {
+            panic!("Salvo fired in a game with moved ships");
+        }
+        if !self.joined.contains(&(data.fleet.clone(), data.initial)) {
+            panic!(
+                "Fleet {} fired from another fleet than it joined with",
+                data.fleet
+            );
+        }
+        let fleet = self.fleet(&data.fleet, &data.board);
+        if fleet.shots != data.shots {
+            panic!(
+                "Shot history of fleet {} does not match its state",
+                data.fleet
+            );
+        }
+        fleet.shots = data.next_shots;
+        if !self.on_turn(&data.fleet) {
+            panic!("Fleet {} fired out of turn", data.fleet);
+        }
+        if !self.fleets.iter().any(|f| f.fleet == data.target) {
+            panic!("Target fleet {} has not joined", data.target);
+        }
+        if self.teammates(&data.fleet, &data.target) {
+            panic!("Fleet {} fired at its teammate {}", data.fleet, data.target);
+        }
+        if self.eliminated.contains(&data.target) {
+            panic!(
+                "Fleet {} fired at the eliminated fleet {}",
+                data.fleet, data.target
+            );
+        }
+        self.next_report = Some(data.target);
+        self.next_player = None;
+        self.last_player = Some(data.fleet);
+        self.last_shot_pos = data.positions.last().copied();
+        self.pending = data.positions;
+        data.gameid
+    }
+
+    fn salvo_report(&mut self, data: SalvoReportJournal) -> String {
+        if self.pending_scan.is_some() {
+            panic!(
+                "Fleet {} reported a salvo instead of answering a scan",
+                data.fleet
+            );
+        }
+        if self.mode != Some(GameMode::Salvo) {
+            panic!("Salvo report in a game without salvos");
+        }
+        if self.next_report.as_ref() != Some(&data.fleet) {
+            panic!("Fleet {} reported out of turn", data.fleet);
+        }
+        if self.pending != data.positions {
+            panic!(
+                "Fleet {} reported other positions than the salvo",
+                data.fleet
+            );
+        }
+        for (&pos, report) in data.positions.iter().zip(&data.reports) {
+            if report == "Hit" {
+                self.hit_cells.push((data.fleet.clone(), pos));
+            } else {
+                self.miss_cells.push((data.fleet.clone(), pos));
+            }
+        }
+        let fleet = self.fleet(&data.fleet, &data.board);
+        fleet.board = data.next_board;
+        fleet.hits += data.reports.iter().filter(|r| r.as_str() == "Hit").count() as u32;
+        let sunk = fleet.hits as usize >= FLEET_CELLS;
+        if sunk && !self.eliminated.contains(&data.fleet) {
+            self.eliminated.push(data.fleet.clone());
+        }
+        self.next_player = Some(data.fleet);
+        self.next_report = None;
+        self.pending.clear();
+        data.gameid
+    }
+
+    fn scan_request(&mut self, data: ScanRequestJournal) -> String {
+        self.fleet(&data.fleet, &data.board);
+        if !self.on_turn(&data.fleet) {
+            panic!("Fleet {} scanned out of turn", data.fleet);
+        }
+        if !self.fleets.iter().any(|f| f.fleet == data.target) {
+            panic!("Target fleet {} has not joined", data.target);
+        }
+        if self.teammates(&data.fleet, &data.target) {
+            panic!("Fleet {} scanned its teammate {}", data.fleet, data.target);
+        }
+        if self.eliminated.contains(&data.target) {
+            panic!(
+                "Fleet {} scanned the eliminated fleet {}",
+                data.fleet, data.target
+            );
+        }
+        self.next_report = Some(data.target.clone());
+        self.next_player = None;
+        self.last_player = Some(data.fleet);
+        self.pending_scan = Some((data.target, data.center));
+        data.gameid
+    }
+
+    fn scan(&mut self, data: ScanJournal) -> String {
+        if self.pending_scan != Some((data.fleet.clone(), data.center)) {
+            panic!(
+                "Fleet {} answered a scan that was not requested",
+                data.fleet
+            );
+        }
+        self.fleet(&data.fleet, &data.board);
+        self.next_player = Some(data.fleet);
+        self.next_report = None;
+        self.pending_scan = None;
+        data.gameid
+    }
+
+    fn wave(&mut self, data: BaseJournal) -> String {
+        self.fleet(&data.fleet, &data.board);
+        if !self.on_turn(&data.fleet) {
+            panic!("Fleet {} waved out of turn", data.fleet);
+        }
+        // The turn goes back to the fleet that played before
+        match self.last_player.replace(data.fleet) {
+            Some(last_player) => self.next_player = Some(last_player),
+            None => panic!("No fleet to pass the turn to"),
+        }
+        data.gameid
+    }
+
+    fn move_ship(&mut self, data: MoveJournal) -> String {
+        if self.mode == Some(GameMode::Salvo) {
+            panic!("Fleet {} moved a ship in a salvo game", data.fleet);
+        }
+        self.moved = true;
+        let mut hits: Vec<u8> = self
+            .hit_cells
+            .iter()
+            .filter(|(fleet, _)| fleet == &data.fleet)
+            .map(|(_, pos)| *pos)
+            .collect();
+        hits.sort_unstable();
+        if hits != data.hits {
+            panic!("Fleet {} moved with other hits than reported", data.fleet);
+        }
+        let mut misses: Vec<u8> = self
+            .miss_cells
+            .iter()
+            .filter(|(fleet, _)| fleet == &data.fleet)
+            .map(|(_, pos)| *pos)
+            .collect();
+        misses.sort_unstable();
+        if misses != data.misses {
+            panic!("Fleet {} moved with other misses than reported", data.fleet);
+        }
+        let fleet = self.fleet(&data.fleet, &data.board);
+        fleet.board = data.next_board;
+        if !self.on_turn(&data.fleet) {
+            panic!("Fleet {} moved out of turn", data.fleet);
+        }
+        // Moving takes the place of firing: the turn goes on as after a wave
+        match self.last_player.replace(data.fleet) {
+            Some(last_player) => self.next_player = Some(last_player),
+            None => panic!("No fleet to pass the turn to"),
+        }
+        data.gameid
+    }
+
+    fn resign(&mut self, data: ResignJournal) -> String {
+        self.fleet(&data.fleet, &data.board);
+        self.resigned.push(data.fleet.clone());
+        if !self.eliminated.contains(&data.fleet) {
+            self.eliminated.push(data.fleet.clone());
+        }
+        // The turn goes to the fleet that played before if it is still in the game, or else
+        // to the first fleet by name that is
+        let in_game = |fleet: &String| !self.eliminated.contains(fleet);
+        let successor = match self.last_player.clone().filter(in_game) {
+            Some(fleet) => Some(fleet),
+            None => {
+                let mut fleets: Vec<&String> = self
+                    .fleets
+                    .iter()
+                    .map(|f| &f.fleet)
+                    .filter(|f| in_game(f))
+                    .collect();
+                fleets.sort();
+                fleets.first().map(|fleet| fleet.to_string())
+            }
+        };
+        let waiting = Some(&data.fleet);
+        if self.next_player.as_ref() == waiting || self.next_report.as_ref() == waiting {
+            self.next_player = successor.clone();
+            self.next_report = None;
+            self.pending.clear();
+            self.pending_scan = None;
+        }
+        if self.last_player.as_ref() == waiting {
+            self.last_player = successor;
+        }
+        data.gameid
+    }
+
+    fn win(&mut self, data: BaseJournal) -> String {
+        self.fleet(&data.fleet, &data.board);
+        // A fleet wins, with its team if it has one, once every fleet outside the team is
+        // eliminated
+        let opponents: Vec<&String> = self
+            .fleets
+            .iter()
+            .map(|f| &f.fleet)
+            .filter(|fleet| *fleet != &data.fleet && !self.teammates(&data.fleet, fleet))
+            .collect();
+        if opponents.is_empty() {
+            panic!("Fleet {} claimed victory without an opponent", data.fleet);
+        }
+        if opponents
+            .iter()
+            .any(|fleet| !self.eliminated.contains(fleet))
+        {
+            panic!("Fleet {} claimed victory with opponents afloat", data.fleet);
+        }
+        if self.winner.is_none() {
+            self.winner = Some(data.fleet);
+        }
+        data.gameid
+    }
+}
@@ -1 +1,5 @@
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+// Version tag of the guests built into this crate; bump it whenever a guest changes so that
+// the chain can tell the image IDs of different releases apart
+pub const METHODS_VERSION: &str = env!("CARGO_PKG_VERSION");